@@ -0,0 +1,140 @@
+use std::collections::{BTreeMap, HashMap};
+
+// Louvain modularity optimization over weighted undirected edges, returns a community id per node
+// Ids are contiguous, starting at zero
+pub fn louvain(node_count: usize, edges: &[(usize, usize, f32)]) -> Vec<usize> {
+    let mut membership = (0..node_count).collect::<Vec<usize>>();
+    let mut count = node_count;
+    let mut edges = edges.to_vec();
+
+    loop {
+        let (local, moved) = one_level(count, &edges);
+        if !moved {
+            break;
+        }
+        let (local, communities) = renumber(&local);
+
+        // Every original node follows the community its super node moved to
+        membership = membership.iter().map(|&c| local[c]).collect();
+
+        // Collapse each community into a single node, internal edges become self loops
+        // Ordered maps keep the result independent of hashing, ties between communities go to the lowest id
+        let mut aggregated = BTreeMap::<(usize, usize), f32>::new();
+        for &(a, b, w) in edges.iter() {
+            let (ca, cb) = (local[a], local[b]);
+            *aggregated.entry((ca.min(cb), ca.max(cb))).or_insert(0.) += w;
+        }
+        edges = aggregated.into_iter().map(|((a, b), w)| (a, b, w)).collect();
+        count = communities;
+    }
+
+    renumber(&membership).0
+}
+
+// Greedily moves single nodes into the neighbouring community with the highest modularity gain
+fn one_level(node_count: usize, edges: &[(usize, usize, f32)]) -> (Vec<usize>, bool) {
+    let mut adjacency = vec![vec![]; node_count];
+    let mut degree = vec![0f32; node_count];
+    for &(a, b, w) in edges {
+        degree[a] += w;
+        degree[b] += w;
+        if a != b {
+            adjacency[a].push((b, w));
+            adjacency[b].push((a, w));
+        }
+    }
+
+    let total: f32 = degree.iter().sum();
+    let mut community = (0..node_count).collect::<Vec<usize>>();
+    if total == 0. {
+        return (community, false);
+    }
+
+    let mut community_degree = degree.clone();
+    let mut moved = false;
+    loop {
+        let mut improved = false;
+
+        for i in 0..node_count {
+            let current = community[i];
+
+            let mut weights = BTreeMap::<usize, f32>::new();
+            for &(n, w) in adjacency[i].iter() {
+                *weights.entry(community[n]).or_insert(0.) += w;
+            }
+
+            community_degree[current] -= degree[i];
+            let gain = |c: usize, w: f32| w - community_degree[c] * degree[i] / total;
+
+            let mut best = current;
+            let mut best_gain = gain(current, weights.get(&current).copied().unwrap_or(0.));
+            for (&c, &w) in weights.iter() {
+                let g = gain(c, w);
+                if g > best_gain + 1e-6 {
+                    best = c;
+                    best_gain = g;
+                }
+            }
+
+            community_degree[best] += degree[i];
+            if best != current {
+                community[i] = best;
+                improved = true;
+                moved = true;
+            }
+        }
+
+        if !improved {
+            break;
+        }
+    }
+
+    (community, moved)
+}
+
+// Maps ids onto 0..n in order of first appearance, returns the mapping and n
+fn renumber(ids: &[usize]) -> (Vec<usize>, usize) {
+    let mut lookup = HashMap::new();
+    let renumbered = ids.iter().map(|id| {
+        let next = lookup.len();
+        *lookup.entry(*id).or_insert(next)
+    }).collect();
+    (renumbered, lookup.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two 4-cliques, 0..4 and 4..8, bridged by a single edge
+    fn bridged_cliques() -> Vec<(usize, usize, f32)> {
+        let mut edges = vec![(3, 4, 1.)];
+        for offset in [0, 4] {
+            for a in 0..4 {
+                for b in a + 1..4 {
+                    edges.push((a + offset, b + offset, 1.));
+                }
+            }
+        }
+        edges
+    }
+
+    #[test]
+    fn splits_bridged_cliques() {
+        assert_eq!(louvain(8, &bridged_cliques()), vec![0, 0, 0, 0, 1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn same_input_gives_same_communities() {
+        let edges = bridged_cliques();
+        let first = louvain(8, &edges);
+        for _ in 0..20 {
+            assert_eq!(louvain(8, &edges), first);
+        }
+    }
+
+    #[test]
+    fn isolated_nodes_keep_their_own_community() {
+        assert_eq!(louvain(3, &[]), vec![0, 1, 2]);
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+use crate::error::MyceliaError;
+
+// Nodes in order of first appearance, labeled by their label attribute or else their id
+pub struct DotGraph {
+    pub labels: Vec<String>,
+    pub edges: Vec<(usize, usize, f32)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Id(String),
+    // Both -- and ->, the direction doesn't matter for the layout
+    Edge,
+    Symbol(char),
+}
+
+fn invalid(x: impl Into<String>) -> MyceliaError {
+    MyceliaError::InvalidGraph(x.into())
+}
+
+// Parses node and edge statements of a graphviz file, attributes other than label and weight are ignored
+// Subgraphs are flattened into the main graph, subgraphs as edge operands are not supported
+pub fn parse(src: &str) -> Result<DotGraph, MyceliaError> {
+    let mut parser = Parser {
+        tokens: tokenize(src)?,
+        pos: 0,
+        ids: HashMap::new(),
+        graph: DotGraph { labels: vec![], edges: vec![] },
+    };
+
+    if parser.peek_keyword("strict") {
+        parser.pos += 1;
+    }
+    if !parser.peek_keyword("graph") && !parser.peek_keyword("digraph") {
+        return Err(invalid("expected graph or digraph"));
+    }
+    parser.pos += 1;
+    if let Some(Token::Id(_)) = parser.peek() {
+        parser.pos += 1;
+    }
+    parser.expect('{')?;
+    parser.statements()?;
+
+    Ok(parser.graph)
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>, MyceliaError> {
+    let chars = src.chars().collect::<Vec<char>>();
+    let is_id_char = |c: char| c.is_alphanumeric() || c == '_' || c == '.';
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '#' || (c == '/' && next == Some('/')) {
+            // Line comments, # lines are preprocessor output
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '/' && next == Some('*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                i += 1;
+            }
+            i += 2;
+        } else if c == '-' && (next == Some('-') || next == Some('>')) {
+            tokens.push(Token::Edge);
+            i += 2;
+        } else if c == '"' {
+            let mut value = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err(invalid("unterminated string")),
+                    Some('"') => break,
+                    // Only quotes and backslashes are escaped, the way export_dot writes them
+                    Some('\\') if matches!(chars.get(i + 1), Some('"') | Some('\\')) => {
+                        value.push(chars[i + 1]);
+                        i += 2;
+                    }
+                    Some(&c) => {
+                        value.push(c);
+                        i += 1;
+                    }
+                }
+            }
+            i += 1;
+            tokens.push(Token::Id(value));
+        } else if c == '<' {
+            // Html strings nest their angle brackets
+            let start = i;
+            let mut depth = 0;
+            while i < chars.len() {
+                match chars[i] {
+                    '<' => depth += 1,
+                    '>' => depth -= 1,
+                    _ => {}
+                }
+                i += 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            if depth != 0 {
+                return Err(invalid("unterminated html string"));
+            }
+            tokens.push(Token::Id(chars[start + 1..i - 1].iter().collect()));
+        } else if is_id_char(c) || c == '-' {
+            let start = i;
+            i += 1;
+            while i < chars.len() && is_id_char(chars[i]) {
+                i += 1;
+            }
+            tokens.push(Token::Id(chars[start..i].iter().collect()));
+        } else if "{}[];,=:".contains(c) {
+            tokens.push(Token::Symbol(c));
+            i += 1;
+        } else {
+            return Err(invalid(format!("unexpected character '{}'", c)));
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    ids: HashMap<String, usize>,
+    graph: DotGraph,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Id(x)) if x.eq_ignore_ascii_case(keyword))
+    }
+
+    fn expect(&mut self, symbol: char) -> Result<(), MyceliaError> {
+        match self.next() {
+            Some(Token::Symbol(c)) if c == symbol => Ok(()),
+            x => Err(invalid(format!("expected '{}', got {:?}", symbol, x))),
+        }
+    }
+
+    fn id(&mut self) -> Result<String, MyceliaError> {
+        match self.next() {
+            Some(Token::Id(x)) => Ok(x),
+            x => Err(invalid(format!("expected an id, got {:?}", x))),
+        }
+    }
+
+    // Ports and compass points only affect how edges are drawn
+    fn skip_port(&mut self) -> Result<(), MyceliaError> {
+        while self.peek() == Some(&Token::Symbol(':')) {
+            self.pos += 1;
+            self.id()?;
+        }
+        Ok(())
+    }
+
+    fn node(&mut self, id: &str) -> usize {
+        if let Some(&index) = self.ids.get(id) {
+            return index;
+        }
+        let index = self.graph.labels.len();
+        self.graph.labels.push(id.to_string());
+        self.ids.insert(id.to_string(), index);
+        index
+    }
+
+    // One or more bracketed lists of key = value pairs
+    fn attributes(&mut self) -> Result<Vec<(String, String)>, MyceliaError> {
+        let mut attributes = vec![];
+        while self.peek() == Some(&Token::Symbol('[')) {
+            self.pos += 1;
+            loop {
+                match self.peek() {
+                    Some(Token::Symbol(']')) => {
+                        self.pos += 1;
+                        break;
+                    }
+                    Some(Token::Symbol(',')) | Some(Token::Symbol(';')) => self.pos += 1,
+                    _ => {
+                        let key = self.id()?;
+                        self.expect('=')?;
+                        attributes.push((key, self.id()?));
+                    }
+                }
+            }
+        }
+        Ok(attributes)
+    }
+
+    // Runs until the closing brace of the current graph or subgraph
+    fn statements(&mut self) -> Result<(), MyceliaError> {
+        loop {
+            match self.next() {
+                None => return Err(invalid("missing closing brace")),
+                Some(Token::Symbol('}')) => return Ok(()),
+                Some(Token::Symbol(';')) | Some(Token::Symbol(',')) => {}
+                Some(Token::Symbol('{')) => self.statements()?,
+                Some(Token::Id(keyword)) if keyword.eq_ignore_ascii_case("subgraph") => {
+                    if let Some(Token::Id(_)) = self.peek() {
+                        self.pos += 1;
+                    }
+                    self.expect('{')?;
+                    self.statements()?;
+                }
+                Some(Token::Id(keyword)) if ["graph", "node", "edge"].iter().any(|k| keyword.eq_ignore_ascii_case(k))
+                    && self.peek() == Some(&Token::Symbol('[')) => {
+                    self.attributes()?;
+                }
+                Some(Token::Id(id)) => {
+                    // Graph attribute
+                    if self.peek() == Some(&Token::Symbol('=')) {
+                        self.pos += 1;
+                        self.id()?;
+                        continue;
+                    }
+
+                    self.skip_port()?;
+                    let mut chain = vec![id];
+                    while self.peek() == Some(&Token::Edge) {
+                        self.pos += 1;
+                        chain.push(self.id()?);
+                        self.skip_port()?;
+                    }
+
+                    let attributes = self.attributes()?;
+                    let attribute = |key: &str| attributes.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+
+                    if chain.len() == 1 {
+                        let index = self.node(&chain[0]);
+                        if let Some(label) = attribute("label") {
+                            self.graph.labels[index] = label.to_string();
+                        }
+                    } else {
+                        let weight = attribute("weight").and_then(|w| w.parse::<f32>().ok()).unwrap_or(1.);
+                        for pair in chain.windows(2) {
+                            let (a, b) = (self.node(&pair[0]), self.node(&pair[1]));
+                            self.graph.edges.push((a, b, weight));
+                        }
+                    }
+                }
+                Some(x) => return Err(invalid(format!("unexpected {:?}", x))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chains_add_an_edge_per_pair() {
+        let graph = parse("digraph g { a -> b -> c [weight=2]; b -- d; }").unwrap();
+
+        assert_eq!(graph.labels, vec!["a", "b", "c", "d"]);
+        assert_eq!(graph.edges, vec![(0, 1, 2.), (1, 2, 2.), (1, 3, 1.)]);
+    }
+
+    #[test]
+    fn labels_and_subgraphs() {
+        let src = r#"
+            strict graph {
+                // Attribute statements and ports are skipped
+                node [shape=box];
+                rankdir = LR;
+                a [label="first \"node\" \\ end"];
+                subgraph cluster { b:n -- <c<b>html</b>> }
+                /* a -- z */
+                "a" -- b
+            }"#;
+        let graph = parse(src).unwrap();
+
+        assert_eq!(graph.labels, vec![r#"first "node" \ end"#, "b", "c<b>html</b>"]);
+        assert_eq!(graph.edges, vec![(1, 2, 1.), (0, 1, 1.)]);
+    }
+
+    #[test]
+    fn malformed_input_is_an_error() {
+        for src in ["graph { a -- b", "graph { a [label=\"x] }", "{ a -- b }", "graph { a -- ; }", "graph { a @ b }"] {
+            assert!(matches!(parse(src), Err(MyceliaError::InvalidGraph(_))), "{}", src);
+        }
+    }
+}
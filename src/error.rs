@@ -0,0 +1,59 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum MyceliaError {
+    ShaderCompilation(String),
+    IndexOutOfBounds { index: usize, len: usize },
+    LengthMismatch { expected: usize, actual: usize },
+    InvalidGraph(String),
+    InvalidParameter { name: &'static str, value: f32 },
+    ImmutableEdgeBuffer,
+    Io(std::io::Error),
+    CapacityExceeded { needed: usize, capacity: usize },
+}
+
+impl fmt::Display for MyceliaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MyceliaError::ShaderCompilation(x) => write!(f, "failed to compile shader\n{}", x),
+            MyceliaError::IndexOutOfBounds { index, len } => {
+                write!(f, "index {} is out of bounds for length {}", index, len)
+            }
+            MyceliaError::LengthMismatch { expected, actual } => {
+                write!(f, "expected {} elements, got {}", expected, actual)
+            }
+            MyceliaError::InvalidGraph(x) => write!(f, "invalid graph: {}", x),
+            MyceliaError::InvalidParameter { name, value } => write!(f, "invalid value {} for {}", value, name),
+            MyceliaError::ImmutableEdgeBuffer => write!(f, "the edge buffer is device local and can't be modified"),
+            MyceliaError::Io(x) => write!(f, "io error: {}", x),
+            MyceliaError::CapacityExceeded { needed, capacity } => {
+                write!(f, "{} elements don't fit a buffer with capacity {}", needed, capacity)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MyceliaError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_variant_displays_its_details() {
+        let cases = [
+            (MyceliaError::ShaderCompilation("syntax error".into()), "failed to compile shader\nsyntax error"),
+            (MyceliaError::IndexOutOfBounds { index: 4, len: 3 }, "index 4 is out of bounds for length 3"),
+            (MyceliaError::LengthMismatch { expected: 2, actual: 5 }, "expected 2 elements, got 5"),
+            (MyceliaError::InvalidGraph("line 1: bad".into()), "invalid graph: line 1: bad"),
+            (MyceliaError::InvalidParameter { name: "theta", value: f32::NAN }, "invalid value NaN for theta"),
+            (MyceliaError::ImmutableEdgeBuffer, "the edge buffer is device local and can't be modified"),
+            (MyceliaError::Io(std::io::Error::new(std::io::ErrorKind::Other, "disk full")), "io error: disk full"),
+            (MyceliaError::CapacityExceeded { needed: 10, capacity: 8 }, "10 elements don't fit a buffer with capacity 8"),
+        ];
+
+        for (error, message) in cases {
+            assert_eq!(error.to_string(), message);
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use std::ops::Div;
+use std::collections::VecDeque;
+use std::ops::{Div, Range};
 use std::process::exit;
 use ash::vk;
 use ash::vk::{BufferUsageFlags, DescriptorBufferInfo, DeviceSize, Image, ImageView, PushConstantRange, ShaderStageFlags, WriteDescriptorSet};
@@ -8,13 +9,14 @@ use cen::graphics::Renderer;
 use cen::graphics::renderer::RenderComponent;
 use cen::vulkan::{Buffer, CommandBuffer, DescriptorSetLayout};
 use cen::vulkan::PipelineErr::ShaderCompilation;
-use glam::{IVec3, IVec4, Vec3, Vec4};
+use glam::{BVec3, IVec3, IVec4, Vec3, Vec4};
 use gpu_allocator::MemoryLocation;
 use petgraph::matrix_graph::Nullable;
 use rand::{random, Rng, SeedableRng};
 use log::error;
 use rand::rngs::StdRng;
-use crate::world::World;
+use crate::error::MyceliaError;
+use crate::world::{SimConfig, World};
 
 #[derive(Debug)]
 #[derive(Copy, Clone)]
@@ -24,6 +26,13 @@ struct Node {
     edge_id: i32,
     velocity: Vec3,
     density: f32,
+    // Bitmask of locked axes, x = 1, y = 2, z = 4
+    axis_lock: u32,
+    // Scales the repulsion a node exerts and divides the force it receives, defaults to 1
+    mass: f32,
+    // Incident edges, counted by the degree pass
+    degree: u32,
+    _padding: [u32; 1],
 }
 
 #[derive(Debug)]
@@ -31,6 +40,173 @@ struct Node {
 struct Edge {
     node0: u32,
     node1: u32,
+    // Scales the pull of the edge, 1 unless the world edge says otherwise
+    weight: f32,
+}
+
+#[derive(Debug)]
+#[derive(Copy, Clone)]
+pub enum CenterMode {
+    // Pull proportional to the distance from the origin
+    Linear = 0,
+    Constant = 1,
+    Quadratic = 2,
+}
+
+// Force model of both passes
+#[derive(Debug)]
+#[derive(Copy, Clone)]
+pub enum LayoutMode {
+    // Inverse distance repulsion and linear springs
+    Spring = 0,
+    // Repulsion scaled by (degree + 1) of both nodes and logarithmic springs, keeps hubs apart
+    ForceAtlas2 = 1,
+}
+
+// How the physics pass turns the summed force into movement
+#[derive(Debug)]
+#[derive(Copy, Clone)]
+pub enum Integrator {
+    // The force moves the node directly, without momentum
+    Position = 0,
+    // The force accelerates the node's velocity, which the damping slows down every pass
+    Velocity = 1,
+}
+
+// What the center pull attracts nodes toward, the center mode shapes the pull by the distance to it
+#[derive(Debug)]
+#[derive(Copy, Clone)]
+pub enum GravityTarget {
+    Point(Vec3),
+    Line { origin: Vec3, direction: Vec3 },
+    Plane { point: Vec3, normal: Vec3 },
+}
+
+// How the synthetic tree picks the parent of every new node
+#[derive(Debug)]
+#[derive(Copy, Clone)]
+pub enum Attachment {
+    // Any existing node, equally likely
+    Random,
+    // Proportional to the degree, gives bushy trees with hubs
+    Preferential,
+    // Fills a tree where every node has this many children, 1 gives a single chain
+    Branching(u32),
+}
+
+#[derive(Debug)]
+#[derive(Copy, Clone)]
+pub enum AnimatedParam {
+    Repulsion,
+    EdgeAttraction,
+}
+
+#[derive(Debug)]
+#[derive(Copy, Clone)]
+pub enum Easing {
+    Linear,
+    EaseInOut,
+}
+
+impl Easing {
+    fn apply(&self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseInOut => t * t * (3. - 2. * t),
+        }
+    }
+}
+
+#[derive(Debug)]
+#[derive(Copy, Clone)]
+pub enum PhysicsBuffer {
+    // Written by the edge pass
+    NodesA,
+    // Written by the physics pass
+    NodesB,
+    Edges,
+}
+
+struct ParamAnimation {
+    param: AnimatedParam,
+    from: f32,
+    to: f32,
+    frames: u32,
+    frame: u32,
+    easing: Easing,
+}
+
+struct Autostop {
+    threshold: f32,
+    patience: u32,
+    calm_frames: u32,
+    params: (f32, f32),
+}
+
+impl Autostop {
+    // Counts the frames in a row that moved less than threshold, returns whether the simulation is stopped
+    // The movement of the frame in which the parameters changed was measured before they took effect, so it is skipped
+    fn update(&mut self, movement: f32, params: (f32, f32)) -> bool {
+        if self.params != params {
+            self.params = params;
+            self.calm_frames = 0;
+        } else if movement < self.threshold {
+            self.calm_frames = (self.calm_frames + 1).min(self.patience);
+        } else {
+            self.calm_frames = 0;
+        }
+        self.is_stopped()
+    }
+
+    fn is_stopped(&self) -> bool {
+        self.calm_frames >= self.patience
+    }
+}
+
+// Every edge after the first attaches a new node to the tree, the attachment picks its parent
+fn synthetic_edges(count: usize, attachment: Attachment, rng: &mut StdRng) -> Vec<Edge> {
+    let mut edges = vec![Edge {node0: 0, node1: 1, weight: 1.}];
+    for _ in 1..count {
+        let child = edges.len() as u32 + 1;
+        let parent = match attachment {
+            Attachment::Random => rng.gen_range(0..child),
+            Attachment::Preferential => {
+                let edge = edges[rng.gen_range(0..edges.len())];
+                if rng.gen::<bool>() { edge.node0 } else { edge.node1 }
+            },
+            Attachment::Branching(k) => (child - 1) / k.max(1),
+        };
+        edges.push(Edge {
+            node0: parent,
+            node1: child,
+            weight: 1.,
+        });
+    }
+    edges
+}
+
+// Inserts the edges and their reverses behind the existing edges of their first node, so sorted stays sorted by node0
+fn insert_sorted_edges(sorted: &mut Vec<Edge>, edges: &[(u32, u32)]) {
+    for &(a, b) in edges {
+        for edge in [Edge { node0: a, node1: b, weight: 1. }, Edge { node0: b, node1: a, weight: 1. }] {
+            let at = sorted.partition_point(|e| e.node0 <= edge.node0);
+            sorted.insert(at, edge);
+        }
+    }
+}
+
+// Total distance between the positions and the previous call's positions, infinite when there is nothing to compare against
+// Clearing last_positions makes the next measurement infinite again
+fn movement_since(last_positions: &mut Vec<Vec3>, positions: &[Vec3]) -> f32 {
+    let movement = if last_positions.len() == positions.len() {
+        positions.iter().zip(last_positions.iter()).map(|(a, b)| (*a - *b).length()).sum()
+    } else {
+        f32::INFINITY
+    };
+
+    last_positions.clear();
+    last_positions.extend_from_slice(positions);
+    movement
 }
 
 struct Pipeline {
@@ -38,17 +214,48 @@ struct Pipeline {
     pipeline: PipelineKey,
 }
 
+// Runs the force simulation on the node and edge buffers, it doesn't draw anything itself
+// Pass node_buffer() and edge_buffer() to a GraphRenderer to visualize the result
 pub struct PhysicsComponent {
     node_count: usize,
     edge_count: usize,
+    edge_capacity: usize,
     node_buffer_a: Option<Buffer>,
     node_buffer_b: Option<Buffer>,
     edge_buffer: Option<Buffer>,
+    device_edge_buffer: Option<Buffer>,
+    edge_buffer_device_local: bool,
     descriptorsetlayout: Option<DescriptorSetLayout>,
     physics_pipeline: Option<Pipeline>,
     edge_pipeline: Option<Pipeline>,
+    bounds_pipeline: Option<Pipeline>,
+    degree_pipeline: Option<Pipeline>,
+    // The degrees are recounted on the next render after the edges changed
+    degrees_dirty: bool,
+    bounds_buffer: Option<Buffer>,
+    // Pushes every pair of nodes apart, only its magnitude matters as the shader squares it
     repulsion: f32,
-    pub edge_attraction: f32,
+    center_mode: CenterMode,
+    max_step: f32,
+    dispatch_chunks: u32,
+    force_threshold: f32,
+    attachment: Attachment,
+    rng: StdRng,
+    animation: Option<ParamAnimation>,
+    autostop: Option<Autostop>,
+    last_positions: Vec<Vec3>,
+    movement: f32,
+    energy_history: Option<(usize, VecDeque<f32>)>,
+    repulsion_enabled: bool,
+    edges_enabled: bool,
+    center_enabled: bool,
+    gravity_target: GravityTarget,
+    rest_length: f32,
+    layout_mode: LayoutMode,
+    integrator: Integrator,
+    damping: f32,
+    // Pulls connected nodes together, set_edge_attraction clamps it to zero or more
+    edge_attraction: f32,
     pub running: bool,
     pub step: bool,
 }
@@ -59,7 +266,25 @@ pub struct PhysicsComponent {
 #[derive(Clone)]
 struct PushConstants {
     nodes: u32,
-    repulsion: f32
+    repulsion: f32,
+    center_mode: u32,
+    max_step: f32,
+    node_offset: u32,
+    force_threshold: f32,
+    // 0 or 1, switches the pull toward the center off
+    center_scale: f32,
+    // 0 for a point, 1 for a line and 2 for a plane, the direction is normalized
+    gravity_kind: u32,
+    gravity_origin: Vec4,
+    gravity_direction: Vec4,
+    // Edges shorter than this push their nodes apart
+    rest_length: f32,
+    layout_mode: u32,
+    // Used entries of the edge buffer, the buffer itself can hold more after set_nodes
+    edge_count: u32,
+    // Fraction of the velocity kept per pass, only read by the velocity integrator
+    damping: f32,
+    integrator: u32,
 }
 
 impl PhysicsComponent {
@@ -69,29 +294,72 @@ impl PhysicsComponent {
             step: false,
             node_count: 10000,
             edge_count: 9000,
+            edge_capacity: 0,
             repulsion: 1.2,
+            center_mode: CenterMode::Linear,
+            max_step: f32::INFINITY,
+            dispatch_chunks: 1,
+            force_threshold: 0.,
+            attachment: Attachment::Random,
+            rng: StdRng::seed_from_u64(3243451135u64),
+            animation: None,
+            autostop: None,
+            last_positions: vec![],
+            movement: f32::INFINITY,
+            energy_history: None,
+            repulsion_enabled: true,
+            edges_enabled: true,
+            center_enabled: true,
+            gravity_target: GravityTarget::Point(Vec3::ZERO),
+            rest_length: 0.,
+            layout_mode: LayoutMode::Spring,
+            integrator: Integrator::Position,
+            damping: 0.9,
             edge_attraction: 0.2,
             node_buffer_a: None,
             node_buffer_b: None,
             edge_buffer: None,
+            device_edge_buffer: None,
+            edge_buffer_device_local: false,
             physics_pipeline: None,
             edge_pipeline: None,
+            bounds_pipeline: None,
+            degree_pipeline: None,
+            degrees_dirty: true,
+            bounds_buffer: None,
             descriptorsetlayout: None,
         }
     }
 
-    pub fn set_nodes(&mut self, world: &World) {
+    // Fails when the world doesn't fit the buffers created by initialize
+    pub fn set_nodes(&mut self, world: &World) -> Result<(), MyceliaError> {
+        let node_capacity = self.node_buffer_b.as_ref().unwrap().size as usize / size_of::<Node>();
+        if world.node_count() > node_capacity {
+            return Err(MyceliaError::CapacityExceeded { needed: world.node_count(), capacity: node_capacity });
+        }
+        if world.edge_count() * 2 > self.edge_capacity {
+            return Err(MyceliaError::CapacityExceeded { needed: world.edge_count() * 2, capacity: self.edge_capacity });
+        }
+
         self.node_count = world.node_count();
         self.edge_count = world.edge_count();
+        self.degrees_dirty = true;
+
+        // The new edges only go to the host buffer, a device local copy is uploaded again on the next render
+        self.device_edge_buffer = None;
 
-        let mut rng = StdRng::seed_from_u64(3243451135u64);
+        // Start from the world's layout, so precomputed positions are kept
         let (_, node_mem, _) = unsafe { self.node_buffer_b.as_mut().unwrap().mapped().align_to_mut::<Node>() };
         for (i, node) in world.nodes().iter().enumerate() {
             node_mem[i] = Node {
                 density: node.level as f32,
-                position: Vec3::new(rng.gen::<f32>(), rng.gen::<f32>(), rng.gen::<f32>()) * 0.2 - 0.1,
+                position: node.pos,
                 edge_id: 0,
                 velocity: Vec3::ZERO,
+                axis_lock: 0,
+                mass: 1.,
+                degree: 0,
+                _padding: [0; 1],
             };
         }
 
@@ -100,6 +368,7 @@ impl PhysicsComponent {
             edges.push(Edge {
                 node0: edge.source().index() as u32,
                 node1: edge.target().index() as u32,
+                weight: edge.weight,
             });
         }
 
@@ -109,7 +378,8 @@ impl PhysicsComponent {
         let mut reverse_edges = edges.clone().iter().map(|edge| {
             Edge {
                 node0: edge.node1,
-                node1: edge.node0
+                node1: edge.node0,
+                weight: edge.weight,
             }
         }).collect::<Vec<Edge>>();
         edges.append(&mut reverse_edges);
@@ -120,21 +390,92 @@ impl PhysicsComponent {
         let (_, edge_mem, _) = unsafe { self.edge_buffer.as_mut().unwrap().mapped().align_to_mut::<Edge>() };
         for (i,e) in edges.iter().enumerate() {
             edge_mem[i] = *e;
-            println!("edge: {} {}", e.node0, e.node1);
         }
 
         // Update nodes
         edges.iter().enumerate().rev().for_each(|(i, edge)| {
             node_mem[edge.node0 as usize].edge_id = (i as u32 + 1) as i32;
-            node_mem[edge.node0 as usize].position = Vec3::new(rng.gen::<f32>() - 0.5, rng.gen::<f32>() - 0.5, rng.gen::<f32>() - 0.5);
         });
+
+        Ok(())
+    }
+
+    // Locked axes don't receive any force, locking z on every node gives a 2D layout
+    pub fn set_axis_lock(&mut self, index: usize, lock: BVec3) -> Result<(), MyceliaError> {
+        if index >= self.node_count {
+            return Err(MyceliaError::IndexOutOfBounds { index, len: self.node_count });
+        }
+
+        let mask = lock.x as u32 | (lock.y as u32) << 1 | (lock.z as u32) << 2;
+        for buffer in [self.node_buffer_a.as_mut().unwrap(), self.node_buffer_b.as_mut().unwrap()] {
+            let (_, node_mem, _) = unsafe { buffer.mapped().align_to_mut::<Node>() };
+            node_mem[index].axis_lock = mask;
+        }
+        Ok(())
+    }
+
+    // A pinned node keeps its position but still repels and pulls the others, takes effect on the next frame
+    pub fn pin(&mut self, index: usize) -> Result<(), MyceliaError> {
+        self.set_axis_lock(index, BVec3::TRUE)
+    }
+
+    // Also releases any axis locks of the node
+    pub fn unpin(&mut self, index: usize) -> Result<(), MyceliaError> {
+        self.set_axis_lock(index, BVec3::FALSE)
+    }
+
+    // One mass per node, heavier nodes push the others away harder and move less themselves
+    pub fn set_masses(&mut self, masses: &[f32]) -> Result<(), MyceliaError> {
+        if masses.len() != self.node_count {
+            return Err(MyceliaError::LengthMismatch { expected: self.node_count, actual: masses.len() });
+        }
+        if let Some(&value) = masses.iter().find(|m| !m.is_finite() || **m <= 0.) {
+            return Err(MyceliaError::InvalidParameter { name: "mass", value });
+        }
+
+        for buffer in [self.node_buffer_a.as_mut().unwrap(), self.node_buffer_b.as_mut().unwrap()] {
+            let (_, node_mem, _) = unsafe { buffer.mapped().align_to_mut::<Node>() };
+            for (node, &mass) in node_mem.iter_mut().zip(masses) {
+                node.mass = mass;
+            }
+        }
+        Ok(())
+    }
+
+    // Only nodes within radius hops of node keep moving, the rest get all axes locked
+    // Moves every node so the focus node sits at the center, expects the nodes of world from set_nodes
+    // Replaces any locks from set_axis_lock, a radius of usize::MAX releases every node again, connected or not
+    pub fn focus(&mut self, world: &World, node: usize, radius: usize) -> Result<(), MyceliaError> {
+        if world.node_count() != self.node_count {
+            return Err(MyceliaError::LengthMismatch { expected: self.node_count, actual: world.node_count() });
+        }
+        if node >= self.node_count {
+            return Err(MyceliaError::IndexOutOfBounds { index: node, len: self.node_count });
+        }
+
+        let distances = world.distances_from(node);
+        for buffer in [self.node_buffer_a.as_mut().unwrap(), self.node_buffer_b.as_mut().unwrap()] {
+            let (_, node_mem, _) = unsafe { buffer.mapped().align_to_mut::<Node>() };
+            let center = node_mem[node].position;
+            for (n, distance) in node_mem.iter_mut().zip(distances.iter()) {
+                // Node is packed, compound assignment would borrow the unaligned field
+                let position = n.position;
+                n.position = position - center;
+                n.axis_lock = match distance {
+                    _ if radius == usize::MAX => 0,
+                    Some(d) if *d <= radius => 0,
+                    _ => 7,
+                };
+            }
+        }
+        Ok(())
     }
 
     pub fn node_buffer(&self) -> DescriptorBufferInfo {
         self.node_buffer_a.as_ref().unwrap().binding()
     }
 
-    fn load_pipeline(renderer: &mut Renderer, path: &str, layout: DescriptorSetLayout, push_constant_range: PushConstantRange) -> PipelineKey {
+    fn load_pipeline(renderer: &mut Renderer, path: &str, layout: DescriptorSetLayout, push_constant_range: PushConstantRange) -> Result<PipelineKey, MyceliaError> {
         match renderer.pipeline_store().insert(PipelineConfig {
             shader_path: path.into(),
             descriptor_set_layouts: vec![
@@ -145,16 +486,215 @@ impl PhysicsComponent {
             ],
             macros: Default::default(),
         }) {
-            Ok(x) => x,
-            Err(ShaderCompilation(x)) => {
-                error!("Failed to create pipeline\n{}", x);
-                exit(1);
-            },
+            Ok(x) => Ok(x),
+            Err(ShaderCompilation(x)) => Err(MyceliaError::ShaderCompilation(x.to_string())),
         }
     }
 
     pub fn edge_buffer(&self) -> DescriptorBufferInfo {
-        self.edge_buffer.as_ref().unwrap().binding()
+        self.device_edge_buffer.as_ref().or(self.edge_buffer.as_ref()).unwrap().binding()
+    }
+
+    // Copies the edges into a device local buffer on the first render and reads them from there
+    // This speeds up the edge pass on discrete gpus, but the edges can't change afterwards
+    // The host copy is kept as the staging buffer, so read_edges keeps working
+    // Turning it off drops the device buffer, so the passes read the host edges again, call while the device is idle
+    pub fn set_edge_buffer_device_local(&mut self, device_local: bool) {
+        self.edge_buffer_device_local = device_local;
+        if !device_local {
+            self.device_edge_buffer = None;
+        }
+    }
+
+    fn upload_device_edges(&mut self, renderer: &mut Renderer, command_buffer: &mut CommandBuffer) {
+        let staging = self.edge_buffer.as_ref().unwrap();
+        let device_buffer = Buffer::new(
+            &renderer.device,
+            &mut renderer.allocator,
+            MemoryLocation::GpuOnly,
+            staging.size,
+            BufferUsageFlags::STORAGE_BUFFER | BufferUsageFlags::TRANSFER_DST
+        );
+
+        unsafe {
+            renderer.device.handle().cmd_copy_buffer(
+                command_buffer.handle(),
+                staging.binding().buffer,
+                device_buffer.binding().buffer,
+                &[vk::BufferCopy::default().size(staging.size)]
+            );
+        }
+
+        command_buffer.buffer_barrier(
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::AccessFlags::TRANSFER_WRITE,
+            vk::AccessFlags::SHADER_READ,
+            vk::DependencyFlags::default(),
+            device_buffer.size,
+            0,
+            &device_buffer
+        );
+
+        self.device_edge_buffer = Some(device_buffer);
+    }
+
+    // Degree of every node as counted on the gpu, valid once the frame after set_nodes or an append has completed
+    pub fn read_degrees(&mut self) -> Vec<u32> {
+        let (_, node_mem, _) = unsafe { self.node_buffer_b.as_mut().unwrap().mapped().align_to_mut::<Node>() };
+        node_mem[..self.node_count].iter().map(|n| n.degree).collect()
+    }
+
+    // Counts the edges of every node into buffer b, the edge pass copies them over to buffer a
+    fn record_degrees(&mut self, renderer: &mut Renderer, command_buffer: &mut CommandBuffer) {
+        let compute = renderer.pipeline_store().get(self.degree_pipeline.as_ref().unwrap().pipeline).unwrap();
+
+        command_buffer.bind_pipeline(&compute);
+
+        let node_bindings = [self.node_buffer_b.as_ref().unwrap().binding()];
+        let node_write_descriptor_set = WriteDescriptorSet::default()
+            .dst_binding(0)
+            .dst_array_element(0)
+            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+            .buffer_info(&node_bindings);
+
+        let edge_bindings = [self.edge_buffer()];
+        let edge_write_descriptor_set = WriteDescriptorSet::default()
+            .dst_binding(1)
+            .dst_array_element(0)
+            .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+            .buffer_info(&edge_bindings);
+
+        command_buffer.bind_push_descriptor(
+            &compute,
+            0,
+            &[node_write_descriptor_set, edge_write_descriptor_set]
+        );
+
+        for (node_offset, groups) in self.dispatch_ranges() {
+            let push_constants = self.push_constants(node_offset);
+            command_buffer.push_constants(
+                &compute,
+                ShaderStageFlags::COMPUTE,
+                0,
+                bytemuck::bytes_of(&push_constants)
+            );
+
+            command_buffer.dispatch(groups, 1, 1 );
+        }
+
+        command_buffer.buffer_barrier(
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::AccessFlags::SHADER_WRITE,
+            vk::AccessFlags::SHADER_READ,
+            vk::DependencyFlags::default(),
+            self.node_buffer_b.as_ref().unwrap().size,
+            0,
+            self.node_buffer_b.as_ref().unwrap()
+        );
+
+        self.degrees_dirty = false;
+    }
+
+    // The edge buffer as (node0, node1) pairs sorted by node0, returns edge_count() entries
+    pub fn read_edges(&mut self) -> Vec<(u32, u32)> {
+        let count = self.edge_count();
+        let (_, edge_mem, _) = unsafe { self.edge_buffer.as_mut().unwrap().mapped().align_to_mut::<Edge>() };
+        edge_mem[..count].iter().map(|e| (e.node0, e.node1)).collect()
+    }
+
+    // Indices into edge_buffer() of the edges starting at node
+    // Edges are sorted by node0 and every node stores one past the index of its first edge, or 0 without edges,
+    // so the start is read from the node and the end takes a walk over the node's own edges, linear in its degree
+    pub fn edge_range(&mut self, node: u32) -> Result<Range<usize>, MyceliaError> {
+        if node as usize >= self.node_count {
+            return Err(MyceliaError::IndexOutOfBounds { index: node as usize, len: self.node_count });
+        }
+
+        let count = self.edge_count();
+        let (_, node_mem, _) = unsafe { self.node_buffer_b.as_mut().unwrap().mapped().align_to_mut::<Node>() };
+        let first = node_mem[node as usize].edge_id;
+        if first <= 0 {
+            return Ok(0..0);
+        }
+
+        let start = (first - 1) as usize;
+        let (_, edge_mem, _) = unsafe { self.edge_buffer.as_mut().unwrap().mapped().align_to_mut::<Edge>() };
+        let end = start + edge_mem[start..count].iter().take_while(|e| e.node0 == node).count();
+        Ok(start..end)
+    }
+
+    // Inserts the edges and their reverses into the sorted edge buffer, call between frames while the device is idle
+    // The edge buffer is only reallocated when it runs out of capacity, the old buffer is freed right away
+    pub fn append_edges_incremental(&mut self, edges: &[(u32, u32)], renderer: &mut Renderer) -> Result<(), MyceliaError> {
+        if self.edge_buffer_device_local {
+            return Err(MyceliaError::ImmutableEdgeBuffer);
+        }
+        let len = self.node_count;
+        if let Some(index) = edges.iter().flat_map(|&(a, b)| [a, b]).map(|i| i as usize).find(|&i| i >= len) {
+            return Err(MyceliaError::IndexOutOfBounds { index, len });
+        }
+
+        let count = self.edge_count();
+        let (_, edge_mem, _) = unsafe { self.edge_buffer.as_mut().unwrap().mapped().align_to_mut::<Edge>() };
+        let mut sorted = edge_mem[..count].to_vec();
+        insert_sorted_edges(&mut sorted, edges);
+        self.edge_count += edges.len();
+
+        if sorted.len() > self.edge_capacity {
+            self.edge_buffer = Some(Buffer::new(
+                &renderer.device,
+                &mut renderer.allocator,
+                MemoryLocation::CpuToGpu,
+                (size_of::<Edge>() * sorted.len()) as DeviceSize,
+                BufferUsageFlags::STORAGE_BUFFER | BufferUsageFlags::TRANSFER_SRC
+            ));
+            self.edge_capacity = sorted.len();
+        }
+
+        let (_, edge_mem, _) = unsafe { self.edge_buffer.as_mut().unwrap().mapped().align_to_mut::<Edge>() };
+        edge_mem[..sorted.len()].copy_from_slice(&sorted);
+
+        // Inserting shifts the edge ranges of every following node
+        let node_count = self.node_count;
+        for buffer in [self.node_buffer_a.as_mut().unwrap(), self.node_buffer_b.as_mut().unwrap()] {
+            let (_, node_mem, _) = unsafe { buffer.mapped().align_to_mut::<Node>() };
+            Self::write_edge_ids(&mut node_mem[..node_count], &sorted);
+        }
+        self.degrees_dirty = true;
+
+        Ok(())
+    }
+
+    fn write_edge_ids(node_mem: &mut [Node], edges: &[Edge]) {
+        node_mem.iter_mut().for_each(|node| node.edge_id = 0);
+        edges.iter().enumerate().rev().for_each(|(i, edge)| {
+            node_mem[edge.node0 as usize].edge_id = (i as u32 + 1) as i32;
+        });
+    }
+
+    // Center and radius of a sphere around all nodes, computed on the gpu by the previous frame
+    pub fn bounding_sphere(&mut self) -> (Vec3, f32) {
+        let (_, bounds, _) = unsafe { self.bounds_buffer.as_mut().unwrap().mapped().align_to_mut::<Vec4>() };
+        let (min, max) = (bounds[0].truncate(), bounds[1].truncate());
+        if min.cmpgt(max).any() {
+            return (Vec3::ZERO, 0.);
+        }
+        ((min + max) / 2., (max - min).length() / 2.)
+    }
+
+    // FNV-1a hash over the used part of a buffer, only meaningful once the gpu work of the frame has completed
+    // The device local edge buffer can't be mapped, its host copy holds the same edges
+    pub fn buffer_checksum(&mut self, which: PhysicsBuffer) -> u64 {
+        let (buffer, size) = match which {
+            PhysicsBuffer::NodesA => (self.node_buffer_a.as_mut().unwrap(), self.node_count * size_of::<Node>()),
+            PhysicsBuffer::NodesB => (self.node_buffer_b.as_mut().unwrap(), self.node_count * size_of::<Node>()),
+            PhysicsBuffer::Edges => (self.edge_buffer.as_mut().unwrap(), self.edge_count * 2 * size_of::<Edge>()),
+        };
+        buffer.mapped()[..size].iter().fold(0xcbf29ce484222325u64, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
     }
 
     pub fn node_count(&mut self) -> &mut usize {
@@ -165,13 +705,283 @@ impl PhysicsComponent {
         self.edge_count * 2
     }
 
+    #[deprecated(note = "use config and set_repulsion, which clamps negative values")]
     pub fn repulsion(&mut self) -> &mut f32 {
         &mut self.repulsion
     }
 
+    pub fn config(&self) -> SimConfig {
+        SimConfig {
+            repulsion: self.repulsion,
+            edge_attraction: self.edge_attraction,
+        }
+    }
+
+    // Both forces keep their direction, negative values are clamped to zero
+    pub fn set_repulsion(&mut self, repulsion: f32) {
+        self.repulsion = repulsion.max(0.);
+    }
+
+    pub fn set_edge_attraction(&mut self, edge_attraction: f32) {
+        self.edge_attraction = edge_attraction.max(0.);
+    }
+
+    pub fn set_config(&mut self, config: &SimConfig) {
+        self.set_repulsion(config.repulsion);
+        self.set_edge_attraction(config.edge_attraction);
+    }
+
+    // Length at which an edge neither pulls nor pushes, 0 by default which makes edges purely attractive
+    pub fn set_rest_length(&mut self, rest_length: f32) {
+        self.rest_length = rest_length.max(0.);
+    }
+
+    // Switch single force terms off without losing their strength, they are combined in the shaders as 0 or 1 multipliers
+    pub fn set_repulsion_enabled(&mut self, enabled: bool) {
+        self.repulsion_enabled = enabled;
+    }
+
+    pub fn set_edges_enabled(&mut self, enabled: bool) {
+        self.edges_enabled = enabled;
+    }
+
+    pub fn set_center_enabled(&mut self, enabled: bool) {
+        self.center_enabled = enabled;
+    }
+
+    // Lines and planes attract along the shortest path to them, so nodes can still spread out over them
+    pub fn set_gravity_target(&mut self, target: GravityTarget) -> Result<(), MyceliaError> {
+        match target {
+            GravityTarget::Line { direction: axis, .. } | GravityTarget::Plane { normal: axis, .. } if !axis.is_finite() || axis.length() == 0. => {
+                Err(MyceliaError::InvalidParameter { name: "gravity axis", value: axis.length() })
+            }
+            _ => {
+                self.gravity_target = target;
+                Ok(())
+            }
+        }
+    }
+
+    // Kind, origin and normalized direction as sent to the shader
+    fn gravity_constants(&self) -> (u32, Vec4, Vec4) {
+        match self.gravity_target {
+            GravityTarget::Point(point) => (0, point.extend(0.), Vec4::ZERO),
+            GravityTarget::Line { origin, direction } => (1, origin.extend(0.), direction.normalize().extend(0.)),
+            GravityTarget::Plane { point, normal } => (2, point.extend(0.), normal.normalize().extend(0.)),
+        }
+    }
+
+    // Shared by every pass, the force passes override repulsion with their own strength
+    fn push_constants(&self, node_offset: u32) -> PushConstants {
+        let (gravity_kind, gravity_origin, gravity_direction) = self.gravity_constants();
+        PushConstants {
+            nodes: self.node_count as u32,
+            repulsion: self.repulsion,
+            center_mode: self.center_mode as u32,
+            max_step: self.max_step,
+            node_offset,
+            force_threshold: self.force_threshold,
+            center_scale: self.center_enabled as u32 as f32,
+            gravity_kind,
+            gravity_origin,
+            gravity_direction,
+            rest_length: self.rest_length,
+            layout_mode: self.layout_mode as u32,
+            edge_count: self.edge_count() as u32,
+            damping: self.damping,
+            integrator: self.integrator as u32,
+        }
+    }
+
+    // ForceAtlas2 uses the degrees counted on the gpu, its strong gravity is the linear center mode
+    pub fn set_layout_mode(&mut self, mode: LayoutMode) {
+        self.layout_mode = mode;
+    }
+
+    pub fn set_center_mode(&mut self, mode: CenterMode) {
+        self.center_mode = mode;
+    }
+
+    // Position keeps the layouts of earlier versions, velocity settles smoother at the cost of some overshoot
+    pub fn set_integrator(&mut self, integrator: Integrator) {
+        self.integrator = integrator;
+    }
+
+    // Clamped to [0, 1], 0 drops the momentum every pass and 1 never slows the nodes down
+    pub fn set_damping(&mut self, damping: f32) {
+        self.damping = damping.max(0.).min(1.);
+    }
+
+    pub fn try_initialize(&mut self, renderer: &mut Renderer) -> Result<(), MyceliaError> {
+        self.create_buffers(renderer);
+        self.physics_pipeline = Some(Self::create_compute_pipeline(renderer, "shaders/physics.comp", 4)?);
+        self.edge_pipeline = Some(Self::create_compute_pipeline(renderer, "shaders/physics_edges.comp", 3)?);
+        self.bounds_pipeline = Some(Self::create_compute_pipeline(renderer, "shaders/bounds.comp", 2)?);
+        self.degree_pipeline = Some(Self::create_compute_pipeline(renderer, "shaders/degree.comp", 2)?);
+        Ok(())
+    }
+
+    // Caps how far a node can move in a single physics pass, unlimited by default
+    pub fn set_max_step(&mut self, max_step: f32) {
+        self.max_step = max_step.max(0.);
+    }
+
+    // Forces shorter than this are dropped in both passes, so nodes come to a full stop near equilibrium
+    pub fn set_force_threshold(&mut self, threshold: f32) {
+        self.force_threshold = threshold.max(0.);
+    }
+
+    // Splits every physics pass into this many dispatches over node ranges
+    pub fn set_dispatch_chunks(&mut self, chunks: u32) {
+        self.dispatch_chunks = chunks.max(1);
+    }
+
+    // Node offset and workgroup count of every dispatch, chunks are whole workgroups so they never overlap
+    fn dispatch_ranges(&self) -> Vec<(u32, u32)> {
+        let groups = self.node_count.div_ceil(128) as u32;
+        let groups_per_chunk = groups.div_ceil(self.dispatch_chunks).max(1);
+        (0..groups).step_by(groups_per_chunk as usize).map(|start| {
+            (start * 128, groups_per_chunk.min(groups - start))
+        }).collect()
+    }
+
+    // Sweeps a parameter from one value to another over the next frames renders
+    pub fn animate_param(&mut self, which: AnimatedParam, from: f32, to: f32, frames: u32, easing: Easing) {
+        self.animation = Some(ParamAnimation {
+            param: which,
+            from,
+            to,
+            frames,
+            frame: 0,
+            easing,
+        });
+    }
+
+    fn advance_animation(&mut self) {
+        let Some(animation) = self.animation.as_mut() else {
+            return;
+        };
+
+        animation.frame = (animation.frame + 1).min(animation.frames);
+        let done = animation.frame == animation.frames;
+        let value = if done {
+            animation.to
+        } else {
+            let t = animation.easing.apply(animation.frame as f32 / animation.frames as f32);
+            animation.from + (animation.to - animation.from) * t
+        };
+
+        match animation.param {
+            AnimatedParam::Repulsion => self.set_repulsion(value),
+            AnimatedParam::EdgeAttraction => self.set_edge_attraction(value),
+        }
+
+        if done {
+            self.animation = None;
+        }
+    }
+
+    // Skips the physics dispatches after patience frames moving less than threshold in total
+    // The movement is read from the previous frame's output, so stopping lags one frame behind
+    // A patience of 0 would stop before any movement was measured and is rejected
+    pub fn set_autostop(&mut self, threshold: f32, patience: u32) -> Result<(), MyceliaError> {
+        if patience == 0 {
+            return Err(MyceliaError::InvalidParameter { name: "patience", value: 0. });
+        }
+
+        self.autostop = Some(Autostop {
+            threshold,
+            patience,
+            calm_frames: 0,
+            params: (self.repulsion, self.edge_attraction),
+        });
+        Ok(())
+    }
+
+    // The positions don't change while stopped, so the movement is measured from scratch again
+    pub fn resume(&mut self) {
+        if let Some(autostop) = self.autostop.as_mut() {
+            autostop.calm_frames = 0;
+            self.restart_movement();
+        }
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.autostop.as_ref().map_or(false, |a| a.is_stopped())
+    }
+
+    fn restart_movement(&mut self) {
+        self.last_positions.clear();
+        self.movement = f32::INFINITY;
+    }
+
+    // Total distance moved by all nodes between the last two measured frames
+    pub fn movement(&self) -> f32 {
+        self.movement
+    }
+
+    // Keeps the movement of the last capacity simulated frames
+    pub fn enable_energy_history(&mut self, capacity: usize) {
+        self.energy_history = Some((capacity, VecDeque::with_capacity(capacity)));
+    }
+
+    // Oldest first
+    pub fn energy_history(&mut self) -> &[f32] {
+        match self.energy_history.as_mut() {
+            Some((_, history)) => history.make_contiguous(),
+            None => &[],
+        }
+    }
+
+    fn record_energy(&mut self) {
+        let Some((capacity, history)) = self.energy_history.as_mut() else {
+            return;
+        };
+
+        // The first frame has nothing to compare against
+        if !self.movement.is_finite() || *capacity == 0 {
+            return;
+        }
+
+        if history.len() == *capacity {
+            history.pop_front();
+        }
+        history.push_back(self.movement);
+    }
+
+    fn measure_movement(&mut self) {
+        let (_, node_mem, _) = unsafe { self.node_buffer_b.as_mut().unwrap().mapped().align_to_mut::<Node>() };
+        let positions = node_mem[..self.node_count].iter().map(|n| n.position).collect::<Vec<Vec3>>();
+        self.movement = movement_since(&mut self.last_positions, &positions);
+    }
+
+    fn update_autostop(&mut self) {
+        let params = (self.repulsion, self.edge_attraction);
+        let Some(autostop) = self.autostop.as_mut() else {
+            return;
+        };
+
+        let changed = autostop.params != params;
+        autostop.update(self.movement, params);
+        if changed {
+            self.restart_movement();
+        }
+    }
+
+    // Shape of the synthetic graph, call before initialize
+    pub fn set_attachment(&mut self, attachment: Attachment) {
+        self.attachment = attachment;
+    }
+
+    // Seeds the initial positions and synthetic edges, call before initialize
+    // set_nodes replaces both with the world's, seed that one with World::new_seeded
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
     fn create_buffers(&mut self, renderer: &mut Renderer) {
 
-        let mut rng = StdRng::seed_from_u64(3243451135u64);
+        let rng = &mut self.rng;
 
         let mut node_buffer_a = Buffer::new(
             &renderer.device,
@@ -197,6 +1007,10 @@ impl PhysicsComponent {
                 edge_id: 0,
                 velocity: Vec3::ZERO,
                 density: 0.,
+                axis_lock: 0,
+                mass: 1.,
+                degree: 0,
+                _padding: [0; 1],
                 // position: Vec3::new(1., 1., 1.) * i as f32 / self.node_count as f32 * 0.2 - 0.1,
             };
         }
@@ -209,23 +1023,18 @@ impl PhysicsComponent {
             &mut renderer.allocator,
             MemoryLocation::CpuToGpu,
             (size_of::<Edge>() * self.edge_count * 2) as DeviceSize,
-            BufferUsageFlags::STORAGE_BUFFER
+            BufferUsageFlags::STORAGE_BUFFER | BufferUsageFlags::TRANSFER_SRC
         );
 
         // Copy edges
-        let mut edges = vec![Edge {node0: 0, node1: 1}];
-        for i in 0..self.edge_count {
-            edges.push(Edge {
-                node0: edges[(rng.gen::<u32>() % edges.len() as u32) as usize].node1,
-                node1: edges.len() as u32 - 1,
-            });
-        };
+        let mut edges = synthetic_edges(self.edge_count, self.attachment, rng);
 
         // Add the reverse edges as well
         let mut reverse_edges = edges.clone().iter().map(|edge| {
             Edge {
                 node0: edge.node1,
-                node1: edge.node0
+                node1: edge.node0,
+                weight: edge.weight,
             }
         }).collect::<Vec<Edge>>();
         edges.append(&mut reverse_edges);
@@ -237,6 +1046,7 @@ impl PhysicsComponent {
         for i in 0..(self.edge_count * 2) {
             edge_mem[i] = edges[i];
         }
+        self.edge_capacity = self.edge_count * 2;
 
         // Set node positions to zero
         let (_, node_mem, _) = unsafe { self.node_buffer_a.as_mut().unwrap().mapped().align_to_mut::<Node>() };
@@ -258,73 +1068,30 @@ impl PhysicsComponent {
         });
 
         self.edge_buffer = Some(edge_buffer);
-    }
 
-    fn create_edge_pipeline(&mut self, renderer: &mut Renderer) {
-        // Layout
-        let layout_bindings = &[
-            vk::DescriptorSetLayoutBinding::default()
-                .binding(0)
-                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
-                .descriptor_count(1)
-                .stage_flags(vk::ShaderStageFlags::COMPUTE ),
-            vk::DescriptorSetLayoutBinding::default()
-                .binding(1)
-                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
-                .descriptor_count(1)
-                .stage_flags(vk::ShaderStageFlags::COMPUTE ),
-            vk::DescriptorSetLayoutBinding::default()
-                .binding(2)
-                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
-                .descriptor_count(1)
-                .stage_flags(vk::ShaderStageFlags::COMPUTE ),
-        ];
-        let descriptorset = DescriptorSetLayout::new_push_descriptor(
+        // Min and max corner, as two vec4s
+        self.bounds_buffer = Some(Buffer::new(
             &renderer.device,
-            layout_bindings
-        );
-
-        let push_constant_range = PushConstantRange::default()
-            .offset(0)
-            .stage_flags(vk::ShaderStageFlags::COMPUTE)
-            .size(size_of::<PushConstants>() as u32);
-
-        // Pipeline
-        let pipeline = Self::load_pipeline(renderer, "shaders/physics_edges.comp", descriptorset.clone(), push_constant_range);
-
-        self.edge_pipeline = Some(Pipeline{
-            pipeline,
-            descriptorsetlayout: descriptorset.clone(),
-        })
+            &mut renderer.allocator,
+            MemoryLocation::CpuToGpu,
+            (size_of::<Vec4>() * 2) as DeviceSize,
+            BufferUsageFlags::STORAGE_BUFFER
+        ));
     }
 
-    fn create_physics_pipeline(&mut self, renderer: &mut Renderer) {
+    // Every binding is a storage buffer and every pass takes the same push constants
+    fn create_compute_pipeline(renderer: &mut Renderer, shader: &str, bindings: u32) -> Result<Pipeline, MyceliaError> {
         // Layout
-        let layout_bindings = &[
-            vk::DescriptorSetLayoutBinding::default()
-                .binding(0)
-                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
-                .descriptor_count(1)
-                .stage_flags(vk::ShaderStageFlags::COMPUTE ),
-            vk::DescriptorSetLayoutBinding::default()
-                .binding(1)
-                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
-                .descriptor_count(1)
-                .stage_flags(vk::ShaderStageFlags::COMPUTE ),
+        let layout_bindings = (0..bindings).map(|binding| {
             vk::DescriptorSetLayoutBinding::default()
-                .binding(2)
+                .binding(binding)
                 .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
                 .descriptor_count(1)
-                .stage_flags(vk::ShaderStageFlags::COMPUTE ),
-            vk::DescriptorSetLayoutBinding::default()
-                .binding(3)
-                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
-                .descriptor_count(1)
-                .stage_flags(vk::ShaderStageFlags::COMPUTE ),
-        ];
+                .stage_flags(vk::ShaderStageFlags::COMPUTE )
+        }).collect::<Vec<_>>();
         let descriptorset = DescriptorSetLayout::new_push_descriptor(
             &renderer.device,
-            layout_bindings
+            &layout_bindings
         );
 
         let push_constant_range = PushConstantRange::default()
@@ -333,23 +1100,47 @@ impl PhysicsComponent {
             .size(size_of::<PushConstants>() as u32);
 
         // Pipeline
-        let pipeline = Self::load_pipeline(renderer, "shaders/physics.comp", descriptorset.clone(), push_constant_range);
+        let pipeline = Self::load_pipeline(renderer, shader, descriptorset.clone(), push_constant_range)?;
 
-        self.physics_pipeline = Some(Pipeline {
+        Ok(Pipeline {
             pipeline,
             descriptorsetlayout: descriptorset
-        });
+        })
     }
 }
 
 impl RenderComponent for PhysicsComponent {
     fn initialize(&mut self, renderer: &mut Renderer) {
-        self.create_buffers(renderer);
-        self.create_physics_pipeline(renderer);
-        self.create_edge_pipeline(renderer);
+        if let Err(e) = self.try_initialize(renderer) {
+            error!("Failed to initialize physics\n{}", e);
+            exit(1);
+        }
     }
 
-    fn render(&mut self, renderer: &mut Renderer, command_buffer: &mut CommandBuffer, swapchain_image: &Image, swapchain_image_view: &ImageView) {
+    fn render(&mut self, renderer: &mut Renderer, command_buffer: &mut CommandBuffer, _: &Image, _: &ImageView) {
+
+        self.advance_animation();
+
+        if self.edge_buffer_device_local && self.device_edge_buffer.is_none() {
+            self.upload_device_edges(renderer, command_buffer);
+        }
+
+        if self.degrees_dirty {
+            self.record_degrees(renderer, command_buffer);
+        }
+
+        if self.autostop.is_some() || self.energy_history.is_some() {
+            self.measure_movement();
+        }
+
+        if self.autostop.is_some() {
+            self.update_autostop();
+            if self.is_stopped() {
+                return;
+            }
+        }
+
+        self.record_energy();
 
         let buffer_bindings_a = [self.node_buffer_a.as_ref().unwrap().binding()];
         let buffer_write_descriptor_set_a = WriteDescriptorSet::default()
@@ -365,7 +1156,7 @@ impl RenderComponent for PhysicsComponent {
             .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
             .buffer_info(&buffer_bindings_b);
 
-        let edge_buffer_bindings = [self.edge_buffer.as_ref().unwrap().binding()];
+        let edge_buffer_bindings = [self.edge_buffer()];
         let edge_buffer_write_descriptor_set = WriteDescriptorSet::default()
             .dst_binding(2)
             .dst_array_element(0)
@@ -385,19 +1176,20 @@ impl RenderComponent for PhysicsComponent {
                 &[buffer_write_descriptor_set_a, buffer_write_descriptor_set_b, edge_buffer_write_descriptor_set]
             );
 
-            let push_constants = PushConstants {
-                nodes: self.node_count as u32,
-                repulsion: self.edge_attraction,
-            };
-            command_buffer.push_constants(
-                &compute,
-                ShaderStageFlags::COMPUTE,
-                0,
-                bytemuck::bytes_of(&push_constants)
-            );
+            for (node_offset, groups) in self.dispatch_ranges() {
+                let push_constants = PushConstants {
+                    repulsion: if self.edges_enabled { self.edge_attraction } else { 0. },
+                    ..self.push_constants(node_offset)
+                };
+                command_buffer.push_constants(
+                    &compute,
+                    ShaderStageFlags::COMPUTE,
+                    0,
+                    bytemuck::bytes_of(&push_constants)
+                );
 
-            let dispatches = self.node_count.div_ceil(128);
-            command_buffer.dispatch(dispatches as u32, 1, 1 );
+                command_buffer.dispatch(groups, 1, 1 );
+            }
 
             command_buffer.buffer_barrier(
                 vk::PipelineStageFlags::COMPUTE_SHADER,
@@ -423,10 +1215,60 @@ impl RenderComponent for PhysicsComponent {
                 &[buffer_write_descriptor_set_a, buffer_write_descriptor_set_b]
             );
 
-            let push_constants = PushConstants {
-                nodes: self.node_count as u32,
-                repulsion: self.repulsion,
-            };
+            for (node_offset, groups) in self.dispatch_ranges() {
+                let push_constants = PushConstants {
+                    repulsion: if self.repulsion_enabled { self.repulsion } else { 0. },
+                    ..self.push_constants(node_offset)
+                };
+                command_buffer.push_constants(
+                    &compute,
+                    ShaderStageFlags::COMPUTE,
+                    0,
+                    bytemuck::bytes_of(&push_constants)
+                );
+
+                command_buffer.dispatch(groups, 1, 1 );
+            }
+
+            command_buffer.buffer_barrier(
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::AccessFlags::SHADER_WRITE,
+                vk::AccessFlags::SHADER_READ,
+                vk::DependencyFlags::default(),
+                self.node_buffer_b.as_ref().unwrap().size,
+                0,
+                self.node_buffer_b.as_ref().unwrap()
+            );
+        }
+
+        // Bounds of the new positions
+        {
+            let compute = renderer.pipeline_store().get(self.bounds_pipeline.as_ref().unwrap().pipeline).unwrap();
+
+            command_buffer.bind_pipeline(&compute);
+
+            let node_bindings = [self.node_buffer_b.as_ref().unwrap().binding()];
+            let node_write_descriptor_set = WriteDescriptorSet::default()
+                .dst_binding(0)
+                .dst_array_element(0)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                .buffer_info(&node_bindings);
+
+            let bounds_bindings = [self.bounds_buffer.as_ref().unwrap().binding()];
+            let bounds_write_descriptor_set = WriteDescriptorSet::default()
+                .dst_binding(1)
+                .dst_array_element(0)
+                .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+                .buffer_info(&bounds_bindings);
+
+            command_buffer.bind_push_descriptor(
+                &compute,
+                0,
+                &[node_write_descriptor_set, bounds_write_descriptor_set]
+            );
+
+            let push_constants = self.push_constants(0);
             command_buffer.push_constants(
                 &compute,
                 ShaderStageFlags::COMPUTE,
@@ -434,8 +1276,183 @@ impl RenderComponent for PhysicsComponent {
                 bytemuck::bytes_of(&push_constants)
             );
 
-            let dispatches = self.node_count.div_ceil(128);
-            command_buffer.dispatch(dispatches as u32, 1, 1 );
+            command_buffer.dispatch(1, 1, 1 );
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn autostop(patience: u32) -> Autostop {
+        Autostop {
+            threshold: 0.01,
+            patience,
+            calm_frames: 0,
+            params: (1., 1.),
+        }
+    }
+
+    #[test]
+    fn autostop_stops_after_patience_calm_frames() {
+        let mut autostop = autostop(3);
+
+        assert!(!autostop.update(0.5, (1., 1.)));
+        assert!(!autostop.update(0., (1., 1.)));
+        assert!(!autostop.update(0., (1., 1.)));
+        assert!(autostop.update(0., (1., 1.)));
+        assert!(autostop.update(0., (1., 1.)));
+        assert!(!autostop.update(0.5, (1., 1.)));
+    }
+
+    // While stopped the positions stay put, so without a restart the movement would keep reading as calm
+    #[test]
+    fn settled_simulation_restarts_after_resume_or_a_parameter_change() {
+        let settled = vec![Vec3::ZERO, Vec3::ONE];
+        let mut physics = PhysicsComponent::new();
+        physics.set_autostop(0.01, 1).unwrap();
+        let frame = |physics: &mut PhysicsComponent| {
+            physics.movement = movement_since(&mut physics.last_positions, &settled);
+            physics.update_autostop();
+            physics.is_stopped()
+        };
+
+        assert!(!frame(&mut physics));
+        assert!(frame(&mut physics));
+
+        physics.resume();
+        assert!(!physics.is_stopped());
+        assert!(!frame(&mut physics));
+        assert!(frame(&mut physics));
+
+        // The frame of the change is skipped and the one after it has nothing to compare against
+        physics.set_repulsion(2.);
+        assert!(!frame(&mut physics));
+        assert!(!frame(&mut physics));
+        assert!(frame(&mut physics));
+    }
+
+    // Neither strength flips the direction of its force, negative values are clamped to zero
+    #[test]
+    fn negative_strengths_are_clamped() {
+        let mut physics = PhysicsComponent::new();
+
+        physics.set_repulsion(-1.);
+        physics.set_edge_attraction(-0.5);
+        assert_eq!((physics.config().repulsion, physics.config().edge_attraction), (0., 0.));
+
+        physics.set_config(&SimConfig { repulsion: 2., edge_attraction: -3. });
+        assert_eq!((physics.config().repulsion, physics.config().edge_attraction), (2., 0.));
+
+        physics.animate_param(AnimatedParam::Repulsion, 1., -1., 2, Easing::Linear);
+        physics.advance_animation();
+        physics.advance_animation();
+        assert_eq!(physics.config().repulsion, 0.);
+    }
+
+    #[test]
+    fn damping_is_clamped_and_reaches_the_shader() {
+        let mut physics = PhysicsComponent::new();
+        assert_eq!({ physics.push_constants(0).integrator }, Integrator::Position as u32);
+
+        physics.set_integrator(Integrator::Velocity);
+        for (damping, expected) in [(0.5, 0.5), (-1., 0.), (2., 1.), (f32::NAN, 0.)] {
+            physics.set_damping(damping);
+            let constants = physics.push_constants(0);
+            assert_eq!(({ constants.damping }, { constants.integrator }), (expected, 1));
+        }
+    }
+
+    fn max_degree(edges: &[Edge]) -> u32 {
+        let mut degrees = vec![0; edges.len() + 1];
+        for edge in edges {
+            degrees[edge.node0 as usize] += 1;
+            degrees[edge.node1 as usize] += 1;
         }
+        degrees.into_iter().max().unwrap()
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn preferential_attachment_grows_hubs() {
+        let random = synthetic_edges(2000, Attachment::Random, &mut StdRng::seed_from_u64(7));
+        let preferential = synthetic_edges(2000, Attachment::Preferential, &mut StdRng::seed_from_u64(7));
+
+        assert_eq!(random.len(), 2000);
+        assert_eq!(preferential.len(), 2000);
+        assert!(max_degree(&preferential) > max_degree(&random));
+    }
+
+    #[test]
+    fn branching_fills_every_parent() {
+        let edges = synthetic_edges(40, Attachment::Branching(3), &mut StdRng::seed_from_u64(7));
+
+        // Every node has a single parent, the first 13 have exactly 3 children
+        let mut children = vec![0; edges.len() + 1];
+        for (i, edge) in edges.iter().enumerate() {
+            assert_eq!(edge.node1 as usize, i + 1);
+            children[edge.node0 as usize] += 1;
+        }
+        assert!(children[..13].iter().all(|&c| c == 3));
+        assert_eq!(children[13], 1);
+    }
+
+    fn empty_nodes(count: usize) -> Vec<Node> {
+        vec![Node {
+            position: Vec3::ZERO,
+            edge_id: 0,
+            velocity: Vec3::ZERO,
+            density: 0.,
+            axis_lock: 0,
+            mass: 1.,
+            degree: 0,
+            _padding: [0; 1],
+        }; count]
+    }
+
+    // Like set_nodes, the reverse edges are appended and the whole list is sorted in one go
+    fn rebuild(pairs: &[(u32, u32)]) -> Vec<Edge> {
+        let mut edges = pairs.iter().map(|&(a, b)| Edge { node0: a, node1: b, weight: 1. }).collect::<Vec<_>>();
+        edges.extend(pairs.iter().map(|&(a, b)| Edge { node0: b, node1: a, weight: 1. }));
+        edges.sort_by(|a, b| a.node0.cmp(&b.node0));
+        edges
+    }
+
+    #[test]
+    fn incremental_insert_matches_a_full_rebuild() {
+        let mut rng = StdRng::seed_from_u64(11);
+        for _ in 0..200 {
+            let node_count = rng.gen_range(1..20u32);
+            let pair = |rng: &mut StdRng| (rng.gen_range(0..node_count), rng.gen_range(0..node_count));
+            let existing = (0..rng.gen_range(0..30)).map(|_| pair(&mut rng)).collect::<Vec<_>>();
+            let appended = (0..rng.gen_range(0..10)).map(|_| pair(&mut rng)).collect::<Vec<_>>();
+
+            let mut incremental = rebuild(&existing);
+            insert_sorted_edges(&mut incremental, &appended);
+            let full = rebuild(&[existing, appended].concat());
+
+            // Edges of a single node may come in another order, the runs must match
+            let endpoints = |edges: &[Edge]| {
+                let mut pairs = edges.iter().map(|e| (e.node0, e.node1)).collect::<Vec<_>>();
+                pairs.sort();
+                pairs
+            };
+            assert!(incremental.windows(2).all(|w| w[0].node0 <= w[1].node0));
+            assert_eq!(endpoints(&incremental), endpoints(&full));
+
+            let (mut incremental_nodes, mut full_nodes) = (empty_nodes(node_count as usize), empty_nodes(node_count as usize));
+            PhysicsComponent::write_edge_ids(&mut incremental_nodes, &incremental);
+            PhysicsComponent::write_edge_ids(&mut full_nodes, &full);
+            let edge_ids = |nodes: &[Node]| nodes.iter().map(|n| n.edge_id).collect::<Vec<_>>();
+            assert_eq!(edge_ids(&incremental_nodes), edge_ids(&full_nodes));
+        }
+    }
+
+    #[test]
+    fn movement_is_infinite_without_previous_positions() {
+        let mut last_positions = vec![];
+
+        assert_eq!(movement_since(&mut last_positions, &[Vec3::ZERO, Vec3::X]), f32::INFINITY);
+        assert_eq!(movement_since(&mut last_positions, &[Vec3::Y, Vec3::X]), 1.);
+        assert_eq!(movement_since(&mut last_positions, &[Vec3::Y]), f32::INFINITY);
+    }
+}
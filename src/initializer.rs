@@ -0,0 +1,77 @@
+use std::f32::consts::PI;
+use glam::Vec3;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+// Supplies the starting position of a node when the world creates it
+// count is the amount of nodes being placed, reset_positions passes the whole world and add_node the count after adding
+pub trait Initializer {
+    fn position(&mut self, index: usize, count: usize) -> Vec3;
+}
+
+// Uniformly random inside an axis aligned cube around the origin
+pub struct RandomCube {
+    size: f32,
+    rng: StdRng,
+}
+
+impl RandomCube {
+    pub fn new(size: f32) -> Self {
+        Self {
+            size,
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    // Gives the same positions on every run
+    pub fn with_seed(size: f32, seed: u64) -> Self {
+        Self {
+            size,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Initializer for RandomCube {
+    fn position(&mut self, _: usize, _: usize) -> Vec3 {
+        Vec3::new(self.rng.gen::<f32>() - 0.5, self.rng.gen::<f32>() - 0.5, self.rng.gen::<f32>() - 0.5) * self.size
+    }
+}
+
+// Evenly spread over the surface of a sphere with a fibonacci spiral
+pub struct Sphere {
+    pub radius: f32,
+}
+
+impl Initializer for Sphere {
+    fn position(&mut self, index: usize, count: usize) -> Vec3 {
+        let golden_angle = PI * (3. - 5f32.sqrt());
+        let y = 1. - 2. * (index as f32 + 0.5) / count.max(1) as f32;
+        let r = (1. - y * y).sqrt();
+        let theta = golden_angle * index as f32;
+        Vec3::new(theta.cos() * r, y, theta.sin() * r) * self.radius
+    }
+}
+
+// Evenly spread over a circle in the xy plane
+pub struct Circle {
+    pub radius: f32,
+}
+
+impl Initializer for Circle {
+    fn position(&mut self, index: usize, count: usize) -> Vec3 {
+        let angle = 2. * PI * index as f32 / count.max(1) as f32;
+        Vec3::new(angle.cos(), angle.sin(), 0.) * self.radius
+    }
+}
+
+// Takes the positions from a list, nodes past its end start at the origin
+pub struct FromSlice {
+    pub positions: Vec<Vec3>,
+}
+
+impl Initializer for FromSlice {
+    fn position(&mut self, index: usize, _: usize) -> Vec3 {
+        self.positions.get(index).copied().unwrap_or(Vec3::ZERO)
+    }
+}
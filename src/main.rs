@@ -1,4 +1,5 @@
 use std::ops::Mul;
+use std::process::exit;
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
 use ash::vk::{Image, ImageView};
@@ -19,6 +20,10 @@ use crate::renderer::{GraphRenderer, RenderNode};
 mod world;
 mod renderer;
 mod gpu_physics;
+mod initializer;
+mod community;
+mod dot;
+mod error;
 
 struct Application {
     physics_components: PhysicsComponent,
@@ -66,7 +71,8 @@ impl Application {
         let screen_scale = Mat4::from_scale(Vec3::new(width * 2., height * 2., 1.));
         let screen_transform = screen_translate * screen_scale;
 
-        let world = World::new();
+        // Fixed seed, so the layout is the same on every run
+        let world = World::new_seeded(3243451135);
         let mut physics_components = PhysicsComponent::new();
 
         Self {
@@ -202,18 +208,22 @@ impl GuiComponent for Application {
             .resizable(true)
             .title_bar(true)
             .show(context, |ui| unsafe {
+                let mut config = self.physics_components.config();
                 ui.label("Edge attraction");
                 ui.add(
-                    Slider::new(&mut self.physics_components.edge_attraction, 0.0..=100.0)
+                    Slider::new(&mut config.edge_attraction, 0.0..=100.0)
                 );
                 ui.label("Repulsion");
                 ui.add(
-                    Slider::new(self.physics_components.repulsion(), 0.0..=4.0)
+                    Slider::new(&mut config.repulsion, 0.0..=4.0)
                 );
+                self.physics_components.set_config(&config);
                 ui.label("Center attraction");
+                let mut center_attraction = lock.center_attraction();
                 ui.add(
-                    Slider::new(lock.get_center_attraction_mut(), 0.0..=20200.0)
+                    Slider::new(&mut center_attraction, 0.0..=20200.0)
                 );
+                lock.set_center_attraction(center_attraction).ok();
 
                 ui.add(Checkbox::new(&mut self.perspective_camera, "Use perspective camera"));
 
@@ -259,7 +269,10 @@ impl RenderComponent for Application {
     fn initialize(&mut self, renderer: &mut Renderer) {
         self.physics_components.initialize(renderer);
         self.graph_renderer.lock().unwrap().initialize(renderer);
-        self.physics_components.set_nodes(&self.world.lock().unwrap());
+        if let Err(e) = self.physics_components.set_nodes(&self.world.lock().unwrap()) {
+            log::error!("Failed to upload the world\n{}", e);
+            exit(1);
+        }
     }
 
     fn render(&mut self, renderer: &mut Renderer, command_buffer: &mut CommandBuffer, swapchain_image: &Image, swapchain_image_view: &ImageView) {
@@ -1,40 +1,103 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet, VecDeque};
+use std::io;
+use std::io::{BufRead, Read, Write};
 use std::ops::Index;
-use std::time::Instant;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use glam::Vec3;
+use ordered_float::OrderedFloat;
 use petgraph::{Directed, Direction};
 use petgraph::graph::{DiGraph, Edge, Edges, NodeIndex, NodeWeightsMut, UnGraph};
 use petgraph::prelude::EdgeRef;
+use petgraph::unionfind::UnionFind;
 use petgraph::visit::NodeCount;
-use rand::random;
+use serde::{Deserialize, Serialize};
+use crate::community::louvain;
+use crate::dot;
+use crate::error::MyceliaError;
+use crate::initializer::{Initializer, RandomCube};
+
+// Largest world the file loaders build, so a single huge index in a file can't exhaust the memory
+const MAX_NODES: usize = 1 << 24;
 
 #[derive(Default)]
-#[derive(Copy)]
 #[derive(Clone)]
 pub struct Node {
     pub pos: Vec3,
-    pub level: u32
+    pub level: u32,
+    // Written to the dot and json exports, empty when the node has no name
+    pub label: String,
 }
 
 impl Node {
-    pub fn new_random(level: u32) -> Node {
-        Node {
-            pos: Vec3::new(random::<f32>() - 0.5, random::<f32>() - 0.5, random::<f32>() - 0.5) * 0.3,
-            level
-        }
-    }
-
     pub fn new(pos: Vec3, level: u32) -> Node {
         Node {
             pos,
-            level
+            level,
+            label: String::new(),
         }
     }
 }
 
+// Layout of the json export
+#[derive(Serialize, Deserialize)]
+struct JsonGraph {
+    node_count: usize,
+    nodes: Vec<JsonNode>,
+    edges: Vec<JsonEdge>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonNode {
+    label: String,
+    level: u32,
+    position: [f32; 3],
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonEdge {
+    source: usize,
+    target: usize,
+    weight: f32,
+}
+
+#[derive(Debug)]
+#[derive(Copy, Clone)]
+pub struct GraphStats {
+    pub node_count: usize,
+    pub edge_count: usize,
+    pub density: f32,
+    pub average_degree: f32,
+    pub component_count: usize,
+}
+
+// Starting parameters for the GPU simulation, see World::suggested_parameters
+#[derive(Debug)]
+#[derive(Copy, Clone)]
+pub struct SimConfig {
+    pub repulsion: f32,
+    pub edge_attraction: f32,
+}
+
+// Copy of a world's graph and parameters, used to roll edits back
+// The graph is shared with the world until either side changes it
+#[derive(Clone)]
+pub struct GraphSnapshot {
+    graph: Arc<DiGraph<Node, f32>>,
+    center_attraction: f32,
+    edge_strength: f32,
+    bh_physics: bool,
+    bh_theta: f32,
+    run_physics: bool,
+}
+
 pub(crate) struct World {
     center_attraction: f32,
     edge_strength: f32,
-    graph: DiGraph<Node, ()>,
+    // Copied on the first change after a snapshot, see Arc::make_mut
+    graph: Arc<DiGraph<Node, f32>>,
+    initializer: Box<dyn Initializer + Send>,
     bh_physics: bool,
     bh_theta: f32,
     run_physics: bool,
@@ -43,8 +106,8 @@ pub(crate) struct World {
 impl World {
     pub fn new() -> Self {
 
-        let mut g = DiGraph::<Node, ()>::new();
-        g.add_node(Node::new_random(0));
+        let mut g = DiGraph::<Node, f32>::new();
+        g.add_node(Node::new(Vec3::ZERO, 0));
 
         let layers = vec![3, 3, 3];
         let mut index = 0;
@@ -66,13 +129,13 @@ impl World {
             }
 
             // Add child_node
-            g.add_node(Node::new_random(stack.len() as u32 + 1));
+            g.add_node(Node::new(Vec3::ZERO, stack.len() as u32 + 1));
             let child_array_index = g.node_count() - 1;
 
             // Add an edge to the child
             let id_a = g.node_indices().nth(index).unwrap();
             let id_b = g.node_indices().nth(child_array_index).unwrap();
-            g.update_edge(id_a, id_b, ());
+            g.update_edge(id_a, id_b, 1.0);
 
             // Add the child's childs if needed
             if stack.len() + 1 < layers.len() {
@@ -85,16 +148,231 @@ impl World {
             child_index = child_index + 1;
         }
 
+        let mut world = Self::from_graph(g);
+        world.reset_positions();
+        world
+    }
+
+    // The demo tree with reproducible positions, nodes added later are placed from the same seeded sequence
+    pub fn new_seeded(seed: u64) -> Self {
+        let mut world = Self::new();
+        world.set_initializer(Box::new(RandomCube::with_seed(0.3, seed)));
+        world.reset_positions();
+        world
+    }
+
+    pub fn empty() -> Self {
+        Self::from_graph(DiGraph::<Node, f32>::new())
+    }
+
+    pub fn with_capacity(nodes: usize, edges: usize) -> Self {
+        Self::from_graph(DiGraph::<Node, f32>::with_capacity(nodes, edges))
+    }
+
+    // Builds a world with node_count nodes placed by the initializer and an edge of weight 1 per pair
+    pub fn from_edges(node_count: usize, edges: &[(usize, usize)]) -> Result<World, MyceliaError> {
+        let mut g = DiGraph::<Node, f32>::with_capacity(node_count, edges.len());
+        for _ in 0..node_count {
+            g.add_node(Node::default());
+        }
+
+        for &(a, b) in edges {
+            for index in [a, b] {
+                if index >= node_count {
+                    return Err(MyceliaError::IndexOutOfBounds { index, len: node_count });
+                }
+            }
+            g.add_edge(NodeIndex::new(a), NodeIndex::new(b), 1.0);
+        }
+
+        let mut world = Self::from_graph(g);
+        world.reset_positions();
+        Ok(world)
+    }
+
+    // Takes over the structure and edge weights, the initializer places the nodes
+    // Weights are checked like add_weighted_edge, non-finite weights are rejected and negative ones clamped to zero
+    pub fn from_petgraph(graph: &DiGraph<(), f32>) -> Result<World, MyceliaError> {
+        let mut g = graph.map(|_, _| Node::default(), |_, &w| w);
+        for w in g.edge_weights_mut() {
+            *w = Self::checked_parameter("weight", *w)?;
+        }
+
+        let mut world = Self::from_graph(g);
+        world.reset_positions();
+        Ok(world)
+    }
+
+    // One edge per line as two node indices separated by whitespace or commas, an optional third column is the weight
+    // Lines starting with # are comments, the world gets max index + 1 nodes placed by the initializer
+    pub fn from_edge_list(text: &str) -> Result<World, MyceliaError> {
+        let mut edges = vec![];
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields = line.split(|c: char| c.is_whitespace() || c == ',').filter(|x| !x.is_empty()).collect::<Vec<_>>();
+            let index = |i: usize| fields.get(i).and_then(|x| x.parse::<usize>().ok());
+            let (Some(a), Some(b)) = (index(0), index(1)) else {
+                return Err(MyceliaError::InvalidGraph(format!("line {}: expected two node indices in '{}'", number + 1, line)));
+            };
+            if a.max(b) >= MAX_NODES {
+                return Err(MyceliaError::InvalidGraph(format!("line {}: node index {} is above the limit of {} nodes", number + 1, a.max(b), MAX_NODES)));
+            }
+            let weight = match fields.get(2) {
+                Some(x) => Self::checked_parameter("weight", x.parse::<f32>().map_err(|_| {
+                    MyceliaError::InvalidGraph(format!("line {}: invalid weight '{}'", number + 1, x))
+                })?)?,
+                None => 1.,
+            };
+            edges.push((a, b, weight));
+        }
+
+        let node_count = edges.iter().map(|&(a, b, _)| a.max(b) + 1).max().unwrap_or(0);
+        let mut g = DiGraph::<Node, f32>::with_capacity(node_count, edges.len());
+        for _ in 0..node_count {
+            g.add_node(Node::default());
+        }
+        for (a, b, w) in edges {
+            g.add_edge(NodeIndex::new(a), NodeIndex::new(b), w);
+        }
+
+        let mut world = Self::from_graph(g);
+        world.reset_positions();
+        Ok(world)
+    }
+
+    // Nodes are numbered in order of first appearance and labeled by their label attribute or else their id
+    // Directed and undirected edges are both kept as a single edge, the initializer places the nodes
+    pub fn from_dot(src: &str) -> Result<World, MyceliaError> {
+        let parsed = dot::parse(src)?;
+
+        let mut g = DiGraph::<Node, f32>::with_capacity(parsed.labels.len(), parsed.edges.len());
+        for label in parsed.labels {
+            g.add_node(Node { label, ..Node::default() });
+        }
+        for (a, b, w) in parsed.edges {
+            g.add_edge(NodeIndex::new(a), NodeIndex::new(b), Self::checked_parameter("weight", w)?);
+        }
+
+        let mut world = Self::from_graph(g);
+        world.reset_positions();
+        Ok(world)
+    }
+
+    // Reads the coordinate format of a Matrix Market file, every entry becomes an edge from row to column
+    // Entries are one based, pattern matrices get a weight of 1, other weights are the absolute value and diagonal entries are dropped
+    // Symmetric files only store one triangle, those entries are added once and not mirrored
+    pub fn from_matrix_market<R: BufRead>(reader: R) -> Result<World, MyceliaError> {
+        let invalid = |x: String| MyceliaError::InvalidGraph(x);
+        let mut lines = reader.lines();
+
+        let header = lines.next().ok_or_else(|| invalid("empty file".into()))?.map_err(MyceliaError::Io)?;
+        let header = header.to_lowercase();
+        let fields = header.split_whitespace().collect::<Vec<_>>();
+        if fields.len() < 4 || fields[0] != "%%matrixmarket" || fields[1] != "matrix" || fields[2] != "coordinate" {
+            return Err(invalid(format!("unsupported header '{}'", header)));
+        }
+        let pattern = fields[3] == "pattern";
+
+        let mut g = DiGraph::<Node, f32>::new();
+        let mut expected = None;
+        let mut entries = 0;
+        for (number, line) in lines.enumerate() {
+            let line = line.map_err(MyceliaError::Io)?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('%') {
+                continue;
+            }
+
+            let values = line.split_whitespace().collect::<Vec<_>>();
+            let parse_index = |x: Option<&&str>| {
+                x.and_then(|x| x.parse::<usize>().ok())
+                    .ok_or_else(|| invalid(format!("line {}: expected an index in '{}'", number + 2, line)))
+            };
+
+            // The first line after the comments holds the dimensions and the amount of entries
+            let Some(count) = expected else {
+                let (rows, columns) = (parse_index(values.first())?, parse_index(values.get(1))?);
+                expected = Some(parse_index(values.get(2))?);
+                if rows.max(columns) > MAX_NODES {
+                    return Err(invalid(format!("line {}: {} nodes are above the limit of {}", number + 2, rows.max(columns), MAX_NODES)));
+                }
+                g.reserve_nodes(rows.max(columns));
+                for _ in 0..rows.max(columns) {
+                    g.add_node(Node::default());
+                }
+                continue;
+            };
+
+            if entries == count {
+                return Err(invalid(format!("line {}: more than {} entries", number + 2, count)));
+            }
+            entries += 1;
+
+            let (row, column) = (parse_index(values.first())?, parse_index(values.get(1))?);
+            for index in [row, column] {
+                if index == 0 || index > g.node_count() {
+                    return Err(invalid(format!("line {}: index {} is out of bounds for {} nodes", number + 2, index, g.node_count())));
+                }
+            }
+            // Matrices like laplacians hold negative entries, their magnitude is the strength of the connection
+            let weight = if pattern {
+                1.
+            } else {
+                let value = values.get(2).and_then(|x| x.parse::<f32>().ok())
+                    .ok_or_else(|| invalid(format!("line {}: expected a value in '{}'", number + 2, line)))?;
+                Self::checked_parameter("weight", value.abs())?
+            };
+
+            if row != column {
+                g.add_edge(NodeIndex::new(row - 1), NodeIndex::new(column - 1), weight);
+            }
+        }
+
+        match expected {
+            None => return Err(invalid("missing the size line".into())),
+            Some(count) if entries < count => return Err(invalid(format!("expected {} entries, got {}", count, entries))),
+            _ => {}
+        }
+
+        let mut world = Self::from_graph(g);
+        world.reset_positions();
+        Ok(world)
+    }
+
+    // Node indices are kept, so results of petgraph's algorithms map straight back onto the world
+    pub fn to_petgraph(&self) -> DiGraph<(), f32> {
+        self.graph.map(|_, _| (), |_, &w| w)
+    }
+
+    fn from_graph(graph: DiGraph<Node, f32>) -> Self {
         Self {
             edge_strength: 20.0,
             center_attraction: 20000.0,
-            graph: g,
+            graph: Arc::new(graph),
+            initializer: Box::new(RandomCube::new(0.3)),
             bh_physics: false,
             bh_theta: 0.5,
             run_physics: true
         }
     }
 
+    // Used for the positions of nodes created from now on
+    pub fn set_initializer(&mut self, initializer: Box<dyn Initializer + Send>) {
+        self.initializer = initializer;
+    }
+
+    // Places every node again with the current initializer
+    pub fn reset_positions(&mut self) {
+        let count = self.graph.node_count();
+        for (i, node) in Arc::make_mut(&mut self.graph).node_weights_mut().enumerate() {
+            node.pos = self.initializer.position(i, count);
+        }
+    }
+
     pub fn bh_physics(&mut self) -> &mut bool {
         &mut self.bh_physics
     }
@@ -103,18 +381,84 @@ impl World {
         &mut self.run_physics
     }
 
+    #[deprecated(note = "use get_theta_mut or set_theta")]
     pub fn get_bh_theta(&mut self) -> &mut f32 {
         &mut self.bh_theta
     }
 
+    // Barnes-Hut opening angle, 0.5 by default, higher is faster and less accurate
+    pub fn theta(&self) -> f32 {
+        self.bh_theta
+    }
+
+    pub fn get_theta_mut(&mut self) -> &mut f32 {
+        &mut self.bh_theta
+    }
+
+    // Negative values are clamped to zero, which opens every cell
+    pub fn set_theta(&mut self, theta: f32) -> Result<(), MyceliaError> {
+        self.bh_theta = Self::checked_parameter("theta", theta)?;
+        Ok(())
+    }
+
+    #[deprecated(note = "use set_center_attraction, which rejects non-finite values")]
     pub fn get_center_attraction_mut(&mut self) -> &mut f32 {
         &mut self.center_attraction
     }
 
+    #[deprecated(note = "use set_edge_strength, which rejects non-finite values")]
     pub fn get_edge_strength(&mut self) -> &mut f32 {
         &mut self.edge_strength
     }
 
+    pub fn center_attraction(&self) -> f32 {
+        self.center_attraction
+    }
+
+    pub fn edge_strength(&self) -> f32 {
+        self.edge_strength
+    }
+
+    // Both are attractive, negative values are clamped to zero
+    pub fn set_center_attraction(&mut self, value: f32) -> Result<(), MyceliaError> {
+        self.center_attraction = Self::checked_parameter("center_attraction", value)?;
+        Ok(())
+    }
+
+    pub fn set_edge_strength(&mut self, value: f32) -> Result<(), MyceliaError> {
+        self.edge_strength = Self::checked_parameter("edge_strength", value)?;
+        Ok(())
+    }
+
+    fn checked_parameter(name: &'static str, value: f32) -> Result<f32, MyceliaError> {
+        if !value.is_finite() {
+            return Err(MyceliaError::InvalidParameter { name, value });
+        }
+        Ok(value.max(0.))
+    }
+
+    // The initializer is not part of the snapshot, it only affects nodes created later
+    // Taking a snapshot doesn't copy the graph, the next edit of the world does
+    pub fn snapshot(&self) -> GraphSnapshot {
+        GraphSnapshot {
+            graph: self.graph.clone(),
+            center_attraction: self.center_attraction,
+            edge_strength: self.edge_strength,
+            bh_physics: self.bh_physics,
+            bh_theta: self.bh_theta,
+            run_physics: self.run_physics,
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: &GraphSnapshot) {
+        self.graph = snapshot.graph.clone();
+        self.center_attraction = snapshot.center_attraction;
+        self.edge_strength = snapshot.edge_strength;
+        self.bh_physics = snapshot.bh_physics;
+        self.bh_theta = snapshot.bh_theta;
+        self.run_physics = snapshot.run_physics;
+    }
+
     pub fn update(&mut self) {
 
         if !self.run_physics {
@@ -123,8 +467,45 @@ impl World {
 
     }
 
+    // Runs update until max_iters iterations ran or the budget is spent, returns the amount of iterations
+    // At least one iteration runs when max_iters is nonzero, even if it alone exceeds the budget
+    pub fn update_budget(&mut self, max_iters: usize, budget: Duration) -> usize {
+        let start = Instant::now();
+        let mut iterations = 0;
+        while iterations < max_iters {
+            self.update();
+            iterations += 1;
+            if start.elapsed() >= budget {
+                break;
+            }
+        }
+        iterations
+    }
+
+    // Runs update and yields the positions after every iteration, use take to bound the amount of frames
+    // Ends after the first frame in which the nodes moved less than 1e-6 in total
+    pub fn frames(&mut self) -> impl Iterator<Item = Vec<Vec3>> + '_ {
+        let mut previous: Option<Vec<Vec3>> = None;
+        let mut converged = false;
+        std::iter::from_fn(move || {
+            if converged {
+                return None;
+            }
+
+            self.update();
+            let positions = self.graph.node_weights().map(|n| n.pos).collect::<Vec<_>>();
+            if let Some(previous) = previous.as_ref() {
+                let movement: f32 = previous.iter().zip(positions.iter()).map(|(a, b)| (*a - *b).length()).sum();
+                converged = movement < 1e-6;
+            }
+
+            previous = Some(positions.clone());
+            Some(positions)
+        })
+    }
+
     pub fn get_mesh(&mut self) -> (Vec<Node>, Vec<(usize, usize)>) {
-        let positions = self.graph.raw_nodes().iter().map(|n| n.weight).collect::<Vec<_>>();
+        let positions = self.graph.raw_nodes().iter().map(|n| n.weight.clone()).collect::<Vec<_>>();
         let edges = self.graph.raw_edges().iter().map(|e| {
             (e.source().index(), e.target().index())
         }).collect::<Vec<(usize, usize)>>();
@@ -139,15 +520,1009 @@ impl World {
         self.graph.edge_count()
     }
 
+    // The initializer gets the node count including the new node, so layouts that spread the nodes over the count,
+    // like Circle and Sphere, crowd the nodes added one by one together, call reset_positions to spread them again
+    pub fn add_node(&mut self, level: u32) -> usize {
+        let index = self.graph.node_count();
+        let pos = self.initializer.position(index, index + 1);
+        Arc::make_mut(&mut self.graph).add_node(Node::new(pos, level)).index()
+    }
+
+    pub fn label(&self, index: usize) -> Option<&str> {
+        self.graph.node_weight(NodeIndex::new(index)).map(|n| n.label.as_str())
+    }
+
+    pub fn set_label(&mut self, index: usize, label: impl Into<String>) -> Result<(), MyceliaError> {
+        let len = self.graph.node_count();
+        let node = Arc::make_mut(&mut self.graph).node_weight_mut(NodeIndex::new(index))
+            .ok_or(MyceliaError::IndexOutOfBounds { index, len })?;
+        node.label = label.into();
+        Ok(())
+    }
+
+    pub fn add_edge(&mut self, a: usize, b: usize) -> Result<(), MyceliaError> {
+        self.add_weighted_edge(a, b, 1.0)
+    }
+
+    // The weight scales the pull of the edge, negative weights are clamped to zero
+    pub fn add_weighted_edge(&mut self, a: usize, b: usize, weight: f32) -> Result<(), MyceliaError> {
+        let len = self.graph.node_count();
+        for index in [a, b] {
+            if index >= len {
+                return Err(MyceliaError::IndexOutOfBounds { index, len });
+            }
+        }
+        let weight = Self::checked_parameter("weight", weight)?;
+
+        Arc::make_mut(&mut self.graph).add_edge(NodeIndex::new(a), NodeIndex::new(b), weight);
+        Ok(())
+    }
+
+    // Later nodes shift down by one so the order is kept, edges touching the node are dropped
+    pub fn remove_node(&mut self, index: usize) -> Result<(), MyceliaError> {
+        let len = self.graph.node_count();
+        if index >= len {
+            return Err(MyceliaError::IndexOutOfBounds { index, len });
+        }
+
+        // Graph::remove_node would move the last node into the gap instead
+        self.graph = Arc::new(self.graph.filter_map(
+            |i, n| (i.index() != index).then(|| n.clone()),
+            |_, &w| Some(w),
+        ));
+        Ok(())
+    }
+
+    // Removes the edges between a and b in either direction, returns how many were removed
+    pub fn remove_edge(&mut self, a: usize, b: usize) -> Result<usize, MyceliaError> {
+        let len = self.graph.node_count();
+        for index in [a, b] {
+            if index >= len {
+                return Err(MyceliaError::IndexOutOfBounds { index, len });
+            }
+        }
+
+        let before = self.graph.edge_count();
+        let (a, b) = (NodeIndex::new(a), NodeIndex::new(b));
+        Arc::make_mut(&mut self.graph).retain_edges(|g, e| {
+            let (source, target) = g.edge_endpoints(e).unwrap();
+            !((source == a && target == b) || (source == b && target == a))
+        });
+        Ok(before - self.graph.edge_count())
+    }
+
+    // Drops self loops and repeated edges, a -> b and b -> a count as the same edge
+    // The first occurrence and its weight are kept, returns the amount of removed edges
+    pub fn dedup_edges(&mut self) -> usize {
+        let mut seen = HashSet::new();
+        let kept = self.graph.raw_edges().iter().filter_map(|e| {
+            let (a, b) = (e.source().index(), e.target().index());
+            (a != b && seen.insert((a.min(b), a.max(b)))).then_some((e.source(), e.target(), e.weight))
+        }).collect::<Vec<_>>();
+
+        let removed = self.graph.edge_count() - kept.len();
+        if removed == 0 {
+            return 0;
+        }
+
+        let graph = Arc::make_mut(&mut self.graph);
+        graph.clear_edges();
+        for (a, b, w) in kept {
+            graph.add_edge(a, b, w);
+        }
+        removed
+    }
+
+    // Edges over the maximum possible amount of undirected edges
+    pub fn density(&self) -> f32 {
+        let n = self.graph.node_count();
+        if n < 2 {
+            return 0.;
+        }
+        let max_edges = n * (n - 1) / 2;
+        self.graph.edge_count() as f32 / max_edges as f32
+    }
+
+    pub fn stats(&self) -> GraphStats {
+        let node_count = self.graph.node_count();
+        let edge_count = self.graph.edge_count();
+        let average_degree = if node_count > 0 {
+            2. * edge_count as f32 / node_count as f32
+        } else {
+            0.
+        };
+
+        GraphStats {
+            node_count,
+            edge_count,
+            density: self.density(),
+            average_degree,
+            component_count: self.connected_components().iter().max().map_or(0, |id| id + 1),
+        }
+    }
+
+    // Like Fruchterman-Reingold's k = sqrt(area / n), with the area the center pull settles at
+    // Repulsion enters the shader squared and is summed over every node, so it scales with 1 / sqrt(n)
+    // The edge pass averages over a node's edges, so neither the degree nor the density changes the
+    // pull on a node, and both terms only depend on the node count
+    pub fn suggested_parameters(&self) -> SimConfig {
+        let n = self.graph.node_count().max(1) as f32;
+        SimConfig {
+            repulsion: 1.2 * (10000. / n).sqrt(),
+            edge_attraction: 0.2,
+        }
+    }
+
+    // Distinct undirected neighbours of every node, without self loops
+    fn adjacency(&self) -> Vec<Vec<usize>> {
+        self.graph.node_indices().map(|i| {
+            let mut neighbours = self.graph.neighbors_undirected(i)
+                .map(|n| n.index())
+                .filter(|&n| n != i.index())
+                .collect::<Vec<usize>>();
+            neighbours.sort_unstable();
+            neighbours.dedup();
+            neighbours
+        }).collect()
+    }
+
+    // Degree over the maximum possible degree
+    pub fn degree_centrality(&self) -> Vec<f32> {
+        let n = self.graph.node_count();
+        let scale = if n > 1 { 1. / (n - 1) as f32 } else { 0. };
+        self.adjacency().iter().map(|neighbours| neighbours.len() as f32 * scale).collect()
+    }
+
+    // Normalized betweenness with Brandes' algorithm over the unweighted undirected graph
+    pub fn betweenness_centrality(&self) -> Vec<f32> {
+        let n = self.graph.node_count();
+        let adjacency = self.adjacency();
+        let mut centrality = vec![0f32; n];
+
+        for source in 0..n {
+            let mut order = vec![];
+            let mut predecessors = vec![vec![]; n];
+            let mut paths = vec![0f32; n];
+            let mut distance = vec![usize::MAX; n];
+            paths[source] = 1.;
+            distance[source] = 0;
+
+            let mut queue = VecDeque::from([source]);
+            while let Some(v) = queue.pop_front() {
+                order.push(v);
+                for &w in adjacency[v].iter() {
+                    if distance[w] == usize::MAX {
+                        distance[w] = distance[v] + 1;
+                        queue.push_back(w);
+                    }
+                    if distance[w] == distance[v] + 1 {
+                        paths[w] += paths[v];
+                        predecessors[w].push(v);
+                    }
+                }
+            }
+
+            // Accumulate dependencies from the farthest nodes back to the source
+            let mut dependency = vec![0f32; n];
+            while let Some(w) = order.pop() {
+                for &v in predecessors[w].iter() {
+                    dependency[v] += paths[v] / paths[w] * (1. + dependency[w]);
+                }
+                if w != source {
+                    centrality[w] += dependency[w];
+                }
+            }
+        }
+
+        // Every pair is counted from both ends, normalize by the amount of pairs excluding the node
+        let scale = if n > 2 { 1. / ((n - 1) * (n - 2)) as f32 } else { 0. };
+        centrality.iter().map(|c| c * scale).collect()
+    }
+
+    // Community id per node from Louvain modularity optimization over the weighted edges
+    pub fn louvain_communities(&self) -> Vec<usize> {
+        let edges = self.graph.raw_edges().iter().map(|e| {
+            (e.source().index(), e.target().index(), e.weight)
+        }).collect::<Vec<_>>();
+        louvain(self.graph.node_count(), &edges)
+    }
+
+    // Component id per node over the undirected edges, numbered in order of each component's first node
+    pub fn connected_components(&self) -> Vec<usize> {
+        let mut sets = UnionFind::<usize>::new(self.graph.node_count());
+        for edge in self.graph.raw_edges() {
+            sets.union(edge.source().index(), edge.target().index());
+        }
+
+        let mut ids = vec![usize::MAX; self.graph.node_count()];
+        let mut count = 0;
+        (0..self.graph.node_count()).map(|i| {
+            let root = sets.find(i);
+            if ids[root] == usize::MAX {
+                ids[root] = count;
+                count += 1;
+            }
+            ids[root]
+        }).collect()
+    }
+
+    // An rgb color per node, every node of a connected component gets the same one
+    // The hue is a hash of the smallest node index in the component rather than of the component id,
+    // so editing one component doesn't recolor the components after it
+    pub fn component_colors(&self) -> Vec<[f32; 3]> {
+        let components = self.connected_components();
+        let mut smallest = vec![usize::MAX; components.len()];
+        for (i, &id) in components.iter().enumerate() {
+            smallest[id] = smallest[id].min(i);
+        }
+
+        components.iter().map(|&id| {
+            hsv_to_rgb(hash_hue(smallest[id]), 0.65, 0.9)
+        }).collect()
+    }
+
+    // Breadth-first order over the undirected adjacency, only reaches the component of start
+    pub fn bfs(&self, start: usize) -> impl Iterator<Item = usize> + '_ {
+        let mut visited = vec![false; self.graph.node_count()];
+        let mut queue = VecDeque::new();
+        if start < visited.len() {
+            visited[start] = true;
+            queue.push_back(start);
+        }
+
+        std::iter::from_fn(move || {
+            let node = queue.pop_front()?;
+            for n in self.graph.neighbors_undirected(NodeIndex::new(node)) {
+                if !visited[n.index()] {
+                    visited[n.index()] = true;
+                    queue.push_back(n.index());
+                }
+            }
+            Some(node)
+        })
+    }
+
+    // Depth-first preorder over the undirected adjacency, only reaches the component of start
+    pub fn dfs(&self, start: usize) -> impl Iterator<Item = usize> + '_ {
+        let mut visited = vec![false; self.graph.node_count()];
+        let mut stack = vec![];
+        if start < visited.len() {
+            stack.push(start);
+        }
+
+        std::iter::from_fn(move || {
+            loop {
+                let node = stack.pop()?;
+                if visited[node] {
+                    continue;
+                }
+                visited[node] = true;
+                for n in self.graph.neighbors_undirected(NodeIndex::new(node)) {
+                    if !visited[n.index()] {
+                        stack.push(n.index());
+                    }
+                }
+                return Some(node);
+            }
+        })
+    }
+
+    // Unweighted hop distance from start to every node, None when unreachable
+    pub fn distances_from(&self, start: usize) -> Vec<Option<usize>> {
+        let mut distances = vec![None; self.graph.node_count()];
+        if start >= distances.len() {
+            return distances;
+        }
+
+        let mut queue = VecDeque::new();
+        distances[start] = Some(0);
+        queue.push_back(start);
+        while let Some(node) = queue.pop_front() {
+            let d = distances[node].unwrap();
+            for n in self.graph.neighbors_undirected(NodeIndex::new(node)) {
+                if distances[n.index()].is_none() {
+                    distances[n.index()] = Some(d + 1);
+                    queue.push_back(n.index());
+                }
+            }
+        }
+
+        distances
+    }
+
+    // Dijkstra over the undirected adjacency with edge weights as lengths, None when unreachable
+    pub fn weighted_distances_from(&self, start: usize) -> Vec<Option<f32>> {
+        let mut distances = vec![None; self.graph.node_count()];
+        if start >= distances.len() {
+            return distances;
+        }
+
+        let mut heap = BinaryHeap::new();
+        distances[start] = Some(0.);
+        heap.push(Reverse((OrderedFloat(0.), start)));
+        while let Some(Reverse((OrderedFloat(d), node))) = heap.pop() {
+            if distances[node].map_or(false, |best| d > best) {
+                continue;
+            }
+
+            for edge in self.graph.edges_directed(NodeIndex::new(node), Direction::Outgoing)
+                .chain(self.graph.edges_directed(NodeIndex::new(node), Direction::Incoming)) {
+                let other = if edge.source().index() == node { edge.target().index() } else { edge.source().index() };
+                let next = d + *edge.weight();
+                if distances[other].map_or(true, |best| next < best) {
+                    distances[other] = Some(next);
+                    heap.push(Reverse((OrderedFloat(next), other)));
+                }
+            }
+        }
+
+        distances
+    }
+
+    // Min and max corner over all node positions, None for an empty world
+    pub fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        self.graph.node_weights().map(|n| (n.pos, n.pos)).reduce(|(min, max), (p, _)| (min.min(p), max.max(p)))
+    }
+
+    pub fn set_positions(&mut self, positions: &[Vec3]) -> Result<(), MyceliaError> {
+        if positions.len() != self.graph.node_count() {
+            return Err(MyceliaError::LengthMismatch {
+                expected: self.graph.node_count(),
+                actual: positions.len(),
+            });
+        }
+
+        for (node, pos) in Arc::make_mut(&mut self.graph).node_weights_mut().zip(positions) {
+            node.pos = *pos;
+        }
+
+        Ok(())
+    }
+
+    // Moves every node the fraction t of the way to its target, t = 0 keeps the layout and t = 1 lands on target
+    pub fn lerp_positions(&mut self, target: &[Vec3], t: f32) -> Result<(), MyceliaError> {
+        if target.len() != self.graph.node_count() {
+            return Err(MyceliaError::LengthMismatch {
+                expected: self.graph.node_count(),
+                actual: target.len(),
+            });
+        }
+
+        // Weighting both ends instead of adding a delta makes the end points exact
+        for (node, pos) in Arc::make_mut(&mut self.graph).node_weights_mut().zip(target) {
+            node.pos = node.pos * (1. - t) + *pos * t;
+        }
+
+        Ok(())
+    }
+
     pub fn nodes(&self) -> Vec<&Node> {
         self.graph.node_weights().collect::<Vec<&Node>>()
     }
 
-    pub fn edges(&self) -> &[Edge<()>] {
+    pub fn edges(&self) -> &[Edge<f32>] {
         self.graph.raw_edges()
     }
 
     pub fn nodes_mut(&mut self) -> NodeWeightsMut<Node> {
-        self.graph.node_weights_mut()
+        Arc::make_mut(&mut self.graph).node_weights_mut()
+    }
+
+    // Writes the layout as an ascii PLY with the nodes as vertices and the graph edges as edge elements
+    pub fn export_ply<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "ply")?;
+        writeln!(writer, "format ascii 1.0")?;
+        writeln!(writer, "element vertex {}", self.graph.node_count())?;
+        writeln!(writer, "property float x")?;
+        writeln!(writer, "property float y")?;
+        writeln!(writer, "property float z")?;
+        writeln!(writer, "element edge {}", self.graph.edge_count())?;
+        writeln!(writer, "property int vertex1")?;
+        writeln!(writer, "property int vertex2")?;
+        writeln!(writer, "end_header")?;
+
+        for node in self.graph.node_weights() {
+            writeln!(writer, "{} {} {}", node.pos.x, node.pos.y, node.pos.z)?;
+        }
+
+        for edge in self.graph.raw_edges() {
+            writeln!(writer, "{} {}", edge.source().index(), edge.target().index())?;
+        }
+
+        Ok(())
+    }
+
+    // Writes a graphviz digraph, labels and positions become node attributes and weights edge attributes
+    pub fn export_dot<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "digraph {{")?;
+
+        for (i, node) in self.graph.node_weights().enumerate() {
+            write!(writer, "    {} [", i)?;
+            if !node.label.is_empty() {
+                write!(writer, "label=\"{}\" ", node.label.replace('\\', "\\\\").replace('"', "\\\""))?;
+            }
+            writeln!(writer, "pos=\"{},{},{}\"];", node.pos.x, node.pos.y, node.pos.z)?;
+        }
+
+        for edge in self.graph.raw_edges() {
+            writeln!(writer, "    {} -> {} [weight={}];", edge.source().index(), edge.target().index(), edge.weight)?;
+        }
+
+        writeln!(writer, "}}")
+    }
+
+    // Writes the nodes with their label, level and position, and the weighted edges by node index
+    pub fn export_json<W: Write>(&self, writer: W) -> io::Result<()> {
+        let graph = JsonGraph {
+            node_count: self.graph.node_count(),
+            nodes: self.graph.node_weights().map(|n| JsonNode {
+                label: n.label.clone(),
+                level: n.level,
+                position: n.pos.to_array(),
+            }).collect(),
+            edges: self.graph.raw_edges().iter().map(|e| JsonEdge {
+                source: e.source().index(),
+                target: e.target().index(),
+                weight: e.weight,
+            }).collect(),
+        };
+        serde_json::to_writer(writer, &graph)?;
+        Ok(())
+    }
+
+    // Reads the output of export_json back, positions are kept so a layout can be resumed
+    pub fn from_json<R: Read>(reader: R) -> Result<World, MyceliaError> {
+        let graph: JsonGraph = serde_json::from_reader(reader).map_err(|e| MyceliaError::InvalidGraph(e.to_string()))?;
+        if graph.nodes.len() != graph.node_count {
+            return Err(MyceliaError::LengthMismatch { expected: graph.node_count, actual: graph.nodes.len() });
+        }
+
+        let mut g = DiGraph::<Node, f32>::with_capacity(graph.node_count, graph.edges.len());
+        for node in graph.nodes {
+            g.add_node(Node {
+                pos: Vec3::from_array(node.position),
+                level: node.level,
+                label: node.label,
+            });
+        }
+
+        for edge in graph.edges {
+            for index in [edge.source, edge.target] {
+                if index >= graph.node_count {
+                    return Err(MyceliaError::IndexOutOfBounds { index, len: graph.node_count });
+                }
+            }
+            g.add_edge(NodeIndex::new(edge.source), NodeIndex::new(edge.target), Self::checked_parameter("weight", edge.weight)?);
+        }
+
+        Ok(Self::from_graph(g))
+    }
+
+    // Appends the nodes and edges of other, returns the index offset of other's nodes
+    pub fn merge(&mut self, other: &World) -> usize {
+        let offset = self.graph.node_count();
+        let graph = Arc::make_mut(&mut self.graph);
+
+        for node in other.graph.node_weights() {
+            graph.add_node(node.clone());
+        }
+
+        for edge in other.graph.raw_edges() {
+            let a = NodeIndex::new(edge.source().index() + offset);
+            let b = NodeIndex::new(edge.target().index() + offset);
+            graph.add_edge(a, b, edge.weight);
+        }
+
+        offset
+    }
+
+    // Extracts the given nodes and the edges among them, returns the new graph and a mapping from new to original index
+    pub fn subgraph(&self, nodes: &[usize]) -> (World, Vec<usize>) {
+        let mut lookup = vec![None; self.graph.node_count()];
+        let mut mapping = vec![];
+        let mut g = DiGraph::<Node, f32>::new();
+
+        for &original in nodes {
+            if original >= lookup.len() || lookup[original].is_some() {
+                continue;
+            }
+            lookup[original] = Some(g.add_node(self.graph[NodeIndex::new(original)].clone()));
+            mapping.push(original);
+        }
+
+        for edge in self.graph.raw_edges() {
+            if let (Some(a), Some(b)) = (lookup[edge.source().index()], lookup[edge.target().index()]) {
+                g.add_edge(a, b, edge.weight);
+            }
+        }
+
+        (Self::from_graph(g), mapping)
+    }
+}
+
+// Murmur3's finalizer, mapped onto [0, 1)
+fn hash_hue(index: usize) -> f32 {
+    let mut h = index as u32;
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2_ae35);
+    h ^= h >> 16;
+    (h >> 8) as f32 / (1u32 << 24) as f32
+}
+
+fn hsv_to_rgb(h: f32, s: f32, v: f32) -> [f32; 3] {
+    let channel = |n: f32| {
+        let k = (n + h * 6.) % 6.;
+        v - v * s * k.min(4. - k).clamp(0., 1.)
+    };
+    [channel(5.), channel(3.), channel(1.)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoints(world: &World) -> Vec<(usize, usize)> {
+        world.edges().iter().map(|e| (e.source().index(), e.target().index())).collect()
+    }
+
+    fn positions(world: &World) -> Vec<Vec3> {
+        world.nodes().iter().map(|n| n.pos).collect()
+    }
+
+    // Places node i at x = i
+    struct OnXAxis;
+
+    impl Initializer for OnXAxis {
+        fn position(&mut self, index: usize, _: usize) -> Vec3 {
+            Vec3::X * index as f32
+        }
+    }
+
+    #[test]
+    fn custom_initializer_places_nodes_on_the_x_axis() {
+        let mut world = World::from_edges(3, &[(0, 1)]).unwrap();
+        world.set_initializer(Box::new(OnXAxis));
+        world.reset_positions();
+        world.add_node(0);
+
+        assert_eq!(positions(&world), (0..4).map(|i| Vec3::X * i as f32).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn merge_offsets_the_edges_of_other() {
+        let mut world = World::from_edges(2, &[(0, 1)]).unwrap();
+        let mut other = World::from_edges(3, &[(0, 2), (1, 2)]).unwrap();
+        let other_positions = vec![Vec3::X, Vec3::Y, Vec3::Z];
+        other.set_positions(&other_positions).unwrap();
+
+        let offset = world.merge(&other);
+
+        assert_eq!(offset, 2);
+        assert_eq!(world.node_count(), 5);
+        assert_eq!(endpoints(&world), vec![(0, 1), (2, 4), (3, 4)]);
+        assert_eq!(positions(&world)[offset..], other_positions[..]);
+    }
+
+    #[test]
+    fn subgraph_keeps_only_edges_inside_the_set() {
+        let world = World::from_edges(5, &[(0, 1), (1, 2), (2, 3), (3, 4), (0, 4)]).unwrap();
+
+        let (sub, mapping) = world.subgraph(&[1, 2, 4]);
+
+        assert_eq!(mapping, vec![1, 2, 4]);
+        assert_eq!(endpoints(&sub), vec![(0, 1)]);
+        for (a, b) in endpoints(&sub) {
+            assert!(a < sub.node_count() && b < sub.node_count());
+            assert!(endpoints(&world).contains(&(mapping[a], mapping[b])));
+        }
+        let original = positions(&world);
+        assert_eq!(positions(&sub), mapping.iter().map(|&i| original[i]).collect::<Vec<_>>());
+    }
+
+    // Petgraph lists the neighbours of a node along its most recently added edge first
+    #[test]
+    fn traversals_visit_the_start_component_in_order() {
+        let world = World::from_edges(6, &[(0, 1), (0, 2), (1, 3), (1, 4)]).unwrap();
+
+        assert_eq!(world.bfs(0).collect::<Vec<_>>(), vec![0, 2, 1, 4, 3]);
+        assert_eq!(world.dfs(0).collect::<Vec<_>>(), vec![0, 1, 3, 4, 2]);
+        assert_eq!(world.bfs(5).collect::<Vec<_>>(), vec![5]);
+        assert_eq!(world.dfs(5).collect::<Vec<_>>(), vec![5]);
+        assert_eq!(world.bfs(6).count(), 0);
+    }
+
+    #[test]
+    fn hop_distances_on_a_path_are_index_offsets() {
+        // A path over the first five nodes and one unreachable node
+        let world = World::from_edges(6, &[(0, 1), (1, 2), (2, 3), (3, 4)]).unwrap();
+
+        for start in 0..5 {
+            let mut expected = (0..5).map(|i: usize| Some(i.abs_diff(start))).collect::<Vec<_>>();
+            expected.push(None);
+            assert_eq!(world.distances_from(start), expected);
+        }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn dijkstra_takes_the_lighter_detour() {
+        let mut world = World::empty();
+        for _ in 0..5 {
+            world.add_node(0);
+        }
+        world.add_weighted_edge(0, 1, 4.).unwrap();
+        world.add_weighted_edge(0, 2, 1.).unwrap();
+        world.add_weighted_edge(2, 1, 2.).unwrap();
+        world.add_weighted_edge(1, 3, 1.).unwrap();
+
+        // 0 -> 2 -> 1 costs 3 instead of 4 for the direct edge, node 4 is unreachable
+        assert_eq!(world.weighted_distances_from(0), vec![Some(0.), Some(3.), Some(1.), Some(4.), None]);
+        assert_eq!(world.weighted_distances_from(3), vec![Some(4.), Some(1.), Some(3.), Some(0.), None]);
+    }
+
+    #[test]
+    fn star_center_has_maximal_centrality() {
+        let world = World::from_edges(5, &[(0, 1), (0, 2), (0, 3), (0, 4)]).unwrap();
+
+        assert_eq!(world.degree_centrality(), vec![1., 0.25, 0.25, 0.25, 0.25]);
+        // Every shortest path between two leaves runs through the center
+        let betweenness = world.betweenness_centrality();
+        assert!((betweenness[0] - 1.).abs() < 1e-6);
+        assert!(betweenness[1..].iter().all(|&b| b == 0.));
+    }
+
+    fn weighted_edges(world: &World) -> Vec<(usize, usize, f32)> {
+        world.edges().iter().map(|e| (e.source().index(), e.target().index(), e.weight)).collect()
+    }
+
+    #[test]
+    fn restore_undoes_every_edit() {
+        let mut world = World::from_edges(3, &[(0, 1)]).unwrap();
+        world.add_weighted_edge(1, 2, 0.5).unwrap();
+        world.set_label(1, "middle").unwrap();
+        world.set_positions(&[Vec3::X, Vec3::Y, Vec3::Z]).unwrap();
+        let before = (positions(&world), weighted_edges(&world), world.label(1).map(String::from));
+        let snapshot = world.snapshot();
+
+        world.add_node(2);
+        world.add_edge(3, 0).unwrap();
+        world.remove_node(1).unwrap();
+        world.set_positions(&[Vec3::ONE; 3]).unwrap();
+        world.set_edge_strength(5.).unwrap();
+        world.restore(&snapshot);
+
+        assert_eq!((positions(&world), weighted_edges(&world), world.label(1).map(String::from)), before);
+        assert_eq!(world.edge_strength(), 20.);
+    }
+
+    #[test]
+    fn snapshots_share_the_graph_until_an_edit() {
+        let mut world = World::from_edges(3, &[(0, 1), (1, 2)]).unwrap();
+        let first = world.snapshot();
+        let second = world.snapshot();
+        assert!(Arc::ptr_eq(&first.graph, &world.graph));
+        assert!(Arc::ptr_eq(&first.graph, &second.graph));
+
+        // Positions live in the nodes, so moving one copies the graph once and leaves the snapshots alone
+        world.set_positions(&[Vec3::ONE; 3]).unwrap();
+        assert!(!Arc::ptr_eq(&first.graph, &world.graph));
+        assert!(Arc::ptr_eq(&first.graph, &second.graph));
+        let copied = Arc::as_ptr(&world.graph);
+        world.set_positions(&[Vec3::ZERO; 3]).unwrap();
+        assert_eq!(Arc::as_ptr(&world.graph), copied);
+
+        world.restore(&first);
+        assert!(Arc::ptr_eq(&first.graph, &world.graph));
+        assert_eq!(world.dedup_edges(), 0);
+        assert!(Arc::ptr_eq(&first.graph, &world.graph));
+    }
+
+    #[test]
+    fn symmetric_matrix_market_adds_each_entry_once() {
+        let mtx = "%%MatrixMarket matrix coordinate real symmetric\n\
+            % Lower triangle of a 3x3 laplacian\n\
+            3 3 5\n\
+            1 1 2.0\n\
+            2 1 -1.5\n\
+            2 2 1.5\n\
+            3 1 -0.5\n\
+            3 2 -1.0\n";
+
+        let world = World::from_matrix_market(mtx.as_bytes()).unwrap();
+
+        // The diagonal is dropped and the negative entries become their magnitude
+        assert_eq!(world.node_count(), 3);
+        assert_eq!(weighted_edges(&world), vec![(1, 0, 1.5), (2, 0, 0.5), (2, 1, 1.)]);
+
+        let truncated = "%%MatrixMarket matrix coordinate real symmetric\n3 3 2\n2 1 1.0\n";
+        assert!(matches!(World::from_matrix_market(truncated.as_bytes()), Err(MyceliaError::InvalidGraph(_))));
+        let infinite = "%%MatrixMarket matrix coordinate real general\n2 2 1\n2 1 inf\n";
+        assert!(matches!(World::from_matrix_market(infinite.as_bytes()), Err(MyceliaError::InvalidParameter { .. })));
+    }
+
+    #[test]
+    fn remove_node_reindexes_later_edges() {
+        let mut world = World::from_edges(5, &[(0, 1), (1, 2), (2, 3), (3, 4), (0, 4)]).unwrap();
+        let original = (0..5).map(|i| Vec3::new(i as f32, 0., 0.)).collect::<Vec<_>>();
+        world.set_positions(&original).unwrap();
+
+        world.remove_node(2).unwrap();
+
+        assert_eq!(world.node_count(), 4);
+        assert_eq!(endpoints(&world), vec![(0, 1), (2, 3), (0, 3)]);
+        // The positions show which original nodes the surviving edges connect
+        let connected = endpoints(&world).iter().map(|&(a, b)| {
+            (world.nodes()[a].pos.x as usize, world.nodes()[b].pos.x as usize)
+        }).collect::<Vec<_>>();
+        assert_eq!(connected, vec![(0, 1), (3, 4), (0, 4)]);
+
+        assert_eq!(world.remove_edge(3, 0).unwrap(), 1);
+        assert_eq!(endpoints(&world), vec![(0, 1), (2, 3)]);
+        assert!(matches!(world.remove_node(4), Err(MyceliaError::IndexOutOfBounds { index: 4, len: 4 })));
+    }
+
+    #[test]
+    fn dedup_drops_self_loops_and_reversed_duplicates() {
+        let mut world = World::empty();
+        for _ in 0..3 {
+            world.add_node(0);
+        }
+        world.add_weighted_edge(0, 1, 2.).unwrap();
+        world.add_weighted_edge(1, 0, 3.).unwrap();
+        world.add_weighted_edge(1, 1, 1.).unwrap();
+        world.add_weighted_edge(1, 2, 1.).unwrap();
+        world.add_weighted_edge(0, 1, 4.).unwrap();
+
+        assert_eq!(world.dedup_edges(), 3);
+        assert_eq!(weighted_edges(&world), vec![(0, 1, 2.), (1, 2, 1.)]);
+        assert_eq!(world.dedup_edges(), 0);
+    }
+
+    #[test]
+    fn components_are_numbered_by_first_node() {
+        let world = World::from_edges(7, &[(4, 3), (0, 1), (2, 1), (6, 3)]).unwrap();
+
+        assert_eq!(world.connected_components(), vec![0, 0, 0, 1, 1, 2, 1]);
+        assert_eq!(world.stats().component_count, 3);
+        assert!(World::empty().connected_components().is_empty());
+    }
+
+    #[test]
+    fn json_round_trip_keeps_the_layout() {
+        let mut world = World::from_edges(3, &[(0, 1)]).unwrap();
+        world.add_weighted_edge(2, 1, 0.25).unwrap();
+        world.set_label(0, "root \"a\"").unwrap();
+        world.set_positions(&[Vec3::new(0.5, -1., 2.), Vec3::ZERO, Vec3::new(0.125, 3., -7.5)]).unwrap();
+
+        let mut json = vec![];
+        world.export_json(&mut json).unwrap();
+        let loaded = World::from_json(json.as_slice()).unwrap();
+
+        assert_eq!(positions(&loaded), positions(&world));
+        assert_eq!(weighted_edges(&loaded), weighted_edges(&world));
+        assert_eq!(loaded.label(0), Some("root \"a\""));
+
+        let dangling = r#"{"node_count":1,"nodes":[{"label":"","level":0,"position":[0,0,0]}],"edges":[{"source":0,"target":1,"weight":1}]}"#;
+        assert!(matches!(World::from_json(dangling.as_bytes()), Err(MyceliaError::IndexOutOfBounds { index: 1, len: 1 })));
+    }
+
+    #[test]
+    fn dot_export_loads_back() {
+        let mut world = World::from_edges(3, &[(0, 1), (1, 2)]).unwrap();
+        for (i, label) in ["plain", "with \"quotes\"", "back\\slash"].iter().enumerate() {
+            world.set_label(i, *label).unwrap();
+        }
+
+        let mut dot = vec![];
+        world.export_dot(&mut dot).unwrap();
+        let loaded = World::from_dot(std::str::from_utf8(&dot).unwrap()).unwrap();
+
+        assert_eq!((0..3).map(|i| loaded.label(i)).collect::<Vec<_>>(), (0..3).map(|i| world.label(i)).collect::<Vec<_>>());
+        assert_eq!(weighted_edges(&loaded), weighted_edges(&world));
+    }
+
+    #[test]
+    fn edge_list_skips_comments_and_sizes_by_max_index() {
+        let text = "# SNAP style header\n\
+            0 1\n\
+            \n\
+            1,3 0.5\n\
+            \t3\t0  \n";
+        let world = World::from_edge_list(text).unwrap();
+
+        assert_eq!(world.node_count(), 4);
+        assert_eq!(weighted_edges(&world), vec![(0, 1, 1.), (1, 3, 0.5), (3, 0, 1.)]);
+        assert_eq!(World::from_edge_list("# nothing\n").unwrap().node_count(), 0);
+    }
+
+    #[test]
+    fn edge_list_errors_name_the_line() {
+        for (text, line) in [
+            ("0 1\n1 x\n", "line 2"),
+            ("0 1 heavy\n", "line 1"),
+            ("0 1\n\n0 4294967295\n", "line 3"),
+            ("0 18446744073709551615\n", "line 1"),
+        ] {
+            match World::from_edge_list(text) {
+                Err(MyceliaError::InvalidGraph(message)) => assert!(message.starts_with(line), "{}", message),
+                _ => panic!("expected an error for {:?}", text),
+            }
+        }
+    }
+
+    #[test]
+    fn complete_graph_has_density_one() {
+        let pairs = (0..5).flat_map(|a| (a + 1..5).map(move |b| (a, b))).collect::<Vec<_>>();
+        let world = World::from_edges(5, &pairs).unwrap();
+
+        assert_eq!(world.density(), 1.);
+        let stats = world.stats();
+        assert_eq!((stats.node_count, stats.edge_count, stats.component_count), (5, 10, 1));
+        assert_eq!((stats.density, stats.average_degree), (1., 4.));
+    }
+
+    #[test]
+    fn positions_survive_an_update_without_forces() {
+        let mut world = World::from_edges(3, &[(0, 1), (1, 2)]).unwrap();
+        let layout = vec![Vec3::new(1., 2., 3.), Vec3::new(-1., 0., 0.5), Vec3::ZERO];
+        world.set_positions(&layout).unwrap();
+        world.set_center_attraction(0.).unwrap();
+        world.set_edge_strength(0.).unwrap();
+
+        world.update();
+
+        assert_eq!(positions(&world), layout);
+        assert!(matches!(world.set_positions(&layout[..2]), Err(MyceliaError::LengthMismatch { expected: 3, actual: 2 })));
+    }
+
+    #[test]
+    fn add_edge_rejects_missing_nodes() {
+        let mut world = World::from_edges(2, &[]).unwrap();
+
+        assert!(matches!(world.add_edge(0, 2), Err(MyceliaError::IndexOutOfBounds { index: 2, len: 2 })));
+        assert!(matches!(world.add_weighted_edge(5, 0, 1.), Err(MyceliaError::IndexOutOfBounds { index: 5, len: 2 })));
+        assert_eq!(world.edge_count(), 0);
+    }
+
+    #[test]
+    fn ply_header_counts_match_the_world() {
+        let world = World::from_edges(4, &[(0, 1), (1, 2)]).unwrap();
+        let mut ply = vec![];
+        world.export_ply(&mut ply).unwrap();
+        let ply = String::from_utf8(ply).unwrap();
+        let lines = ply.lines().collect::<Vec<_>>();
+
+        assert!(lines.contains(&"element vertex 4"));
+        assert!(lines.contains(&"element edge 2"));
+        let body = &lines[lines.iter().position(|l| *l == "end_header").unwrap() + 1..];
+        assert_eq!(body.len(), 6);
+        assert_eq!(body[4..], ["0 1", "1 2"]);
+    }
+
+    #[test]
+    fn checked_setters_reject_non_finite_values() {
+        let mut world = World::from_edges(2, &[]).unwrap();
+
+        for value in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            assert!(matches!(world.set_theta(value), Err(MyceliaError::InvalidParameter { name: "theta", .. })));
+            assert!(matches!(world.set_center_attraction(value), Err(MyceliaError::InvalidParameter { name: "center_attraction", .. })));
+            assert!(matches!(world.set_edge_strength(value), Err(MyceliaError::InvalidParameter { name: "edge_strength", .. })));
+            assert!(matches!(world.add_weighted_edge(0, 1, value), Err(MyceliaError::InvalidParameter { name: "weight", .. })));
+        }
+        assert_eq!((world.theta(), world.center_attraction(), world.edge_strength()), (0.5, 20000., 20.));
+        assert_eq!(world.edge_count(), 0);
+
+        world.set_edge_strength(-1.).unwrap();
+        assert_eq!(world.edge_strength(), 0.);
+    }
+
+    #[test]
+    fn suggested_repulsion_falls_with_the_node_count() {
+        let repulsion = |n| World::from_edges(n, &[]).unwrap().suggested_parameters().repulsion;
+        let values = [1, 10, 100, 1000, 10000].map(repulsion);
+
+        assert!(values.windows(2).all(|w| w[0] > w[1]), "{:?}", values);
+    }
+
+    #[test]
+    fn update_budget_stops_at_the_iteration_cap() {
+        let mut world = World::from_edges(10, &[(0, 1)]).unwrap();
+
+        assert_eq!(world.update_budget(25, Duration::from_secs(60)), 25);
+        assert_eq!(world.update_budget(0, Duration::from_secs(60)), 0);
+        assert_eq!(world.update_budget(25, Duration::ZERO), 1);
+    }
+
+    #[test]
+    fn petgraph_round_trip_keeps_counts_and_weights() {
+        let mut world = World::from_edges(4, &[(0, 1), (2, 3)]).unwrap();
+        world.add_weighted_edge(1, 2, 0.5).unwrap();
+
+        let graph = world.to_petgraph();
+        assert_eq!((graph.node_count(), graph.edge_count()), (4, 3));
+
+        let back = World::from_petgraph(&graph).unwrap();
+        assert_eq!((back.node_count(), back.edge_count()), (4, 3));
+        assert_eq!(weighted_edges(&back), weighted_edges(&world));
+    }
+
+    #[test]
+    fn component_colors_match_within_and_differ_across_components() {
+        let world = World::from_edges(7, &[(0, 1), (1, 2), (3, 4), (6, 4)]).unwrap();
+        let components = world.connected_components();
+        let colors = world.component_colors();
+
+        for a in 0..7 {
+            for b in 0..7 {
+                assert_eq!(components[a] == components[b], colors[a] == colors[b], "nodes {} and {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn frames_end_at_convergence() {
+        let mut world = World::from_edges(3, &[(0, 1)]).unwrap();
+
+        // update doesn't move the nodes, so the second frame already matches the first
+        let frames = world.frames().take(100).collect::<Vec<_>>();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0], frames[1]);
+        assert_eq!(frames[1], positions(&world));
+    }
+
+    #[test]
+    fn lerp_hits_both_ends_exactly() {
+        let start = vec![Vec3::new(0.1, 0.2, 0.3), Vec3::new(-5., 7., 1e-3)];
+        let target = vec![Vec3::new(3., -1., 0.7), Vec3::new(0.25, 0.5, -9.)];
+        let mut world = World::from_edges(2, &[(0, 1)]).unwrap();
+        world.set_positions(&start).unwrap();
+
+        world.lerp_positions(&target, 0.).unwrap();
+        assert_eq!(positions(&world), start);
+        world.lerp_positions(&target, 1.).unwrap();
+        assert_eq!(positions(&world), target);
+
+        assert!(matches!(world.lerp_positions(&target[..1], 0.5), Err(MyceliaError::LengthMismatch { expected: 2, actual: 1 })));
+    }
+
+    #[test]
+    fn from_edges_rejects_out_of_range_indices() {
+        assert!(matches!(World::from_edges(3, &[(0, 1), (1, 3)]), Err(MyceliaError::IndexOutOfBounds { index: 3, len: 3 })));
+        assert!(matches!(World::from_edges(0, &[(0, 0)]), Err(MyceliaError::IndexOutOfBounds { index: 0, len: 0 })));
+
+        let world = World::from_edges(3, &[(2, 0)]).unwrap();
+        assert_eq!(endpoints(&world), vec![(2, 0)]);
+    }
+
+    #[test]
+    fn component_colors_survive_an_edit_of_a_lower_component() {
+        let before = World::from_edges(5, &[(0, 1), (3, 4)]).unwrap();
+        let after = World::from_edges(5, &[(0, 1), (1, 2), (3, 4)]).unwrap();
+
+        // Merging node 2 into the first component renumbers the component of 3 and 4
+        assert_ne!(before.connected_components()[3], after.connected_components()[3]);
+        let (before, after) = (before.component_colors(), after.component_colors());
+        assert_eq!(before[0], after[0]);
+        assert_eq!(before[3], after[3]);
+        assert_eq!(after[2], after[0]);
+    }
+
+    #[test]
+    fn edge_list_rejects_indices_past_the_node_limit() {
+        let text = format!("0 1\n{} 0\n", MAX_NODES);
+        match World::from_edge_list(&text) {
+            Err(MyceliaError::InvalidGraph(message)) => assert!(message.starts_with("line 2") && message.ends_with("nodes"), "{}", message),
+            _ => panic!("expected an error for an index of {}", MAX_NODES),
+        }
+    }
+
+    #[test]
+    fn matrix_market_rejects_sizes_past_the_node_limit() {
+        let mtx = format!("%%MatrixMarket matrix coordinate pattern general\n2 {} 1\n1 2\n", MAX_NODES + 1);
+        match World::from_matrix_market(mtx.as_bytes()) {
+            Err(MyceliaError::InvalidGraph(message)) => assert!(message.starts_with("line 2"), "{}", message),
+            _ => panic!("expected an error for {} columns", MAX_NODES + 1),
+        }
+    }
+}